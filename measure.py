@@ -1,6 +1,7 @@
 # measure.py
 
 import primaldimer_py
+import random
 import time
 
 NUM_ITER = 1000
@@ -23,3 +24,23 @@ t1 = time.perf_counter()
 
 took = (t1 - t0) / NUM_ITER
 print(f"Took and avg of {took * 1000:.2f}ms per iteration")
+
+# A realistic pool, of 96 kmers with 1-3 seqs of 20-30 bases
+random.seed(42)
+pool = []
+for i in range(96):
+    base = "".join(random.choice("ACGT") for _ in range(random.randint(20, 30)))
+    seqs = {base}
+    for _ in range(random.randint(0, 2)):
+        pos = random.randrange(len(base))
+        seqs.add(base[:pos] + random.choice("ACGT") + base[pos + 1 :])
+    pool.append(primaldimer_py.Kmer(i, [*seqs]))
+
+POOL_ITER = 10
+t0 = time.perf_counter()
+for _ in range(POOL_ITER):
+    primaldimer_py.which_kmers_pools_interact(pool, pool, -26, True)
+t1 = time.perf_counter()
+
+took = (t1 - t0) / POOL_ITER
+print(f"Took and avg of {took * 1000:.2f}ms per pool check")
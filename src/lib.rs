@@ -1,34 +1,157 @@
 mod primaldimer;
 
+use pyo3::exceptions::{PyIOError, PyValueError};
 use pyo3::prelude::*;
 
 #[pyclass(subclass)]
 pub struct Kmer {
     #[pyo3(get)]
     pub encodedseqs: Vec<Vec<usize>>,
+    // Optional coordinates on the reference. 0-based, half open
+    #[pyo3(get, set)]
+    pub start: Option<usize>,
+    #[pyo3(get, set)]
+    pub end: Option<usize>,
+    pub strand: Option<primaldimer::Strand>,
+    // Optional primer name, used to label exported results
+    #[pyo3(get, set)]
+    pub name: Option<String>,
+    // The number of 5' bases of each seq that are an adapter tail
+    #[pyo3(get, set)]
+    pub tail_len: usize,
+    // A probe or blocked oligo, whose 3' end can't extend. It can still be a template
+    #[pyo3(get, set)]
+    pub blocked_3p: bool,
+    // RNA rather than DNA. Set if the seqs contain U
+    #[pyo3(get)]
+    pub rna: bool,
 }
-#[pymethods]
 impl Kmer {
-    #[new]
     pub fn new(_idx: usize, seqs: Vec<String>) -> Self {
-        // Check that the sequences are valid
+        Kmer::try_new(_idx, seqs).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_new(_idx: usize, seqs: Vec<String>) -> Result<Self, String> {
+        // Check that the sequences are valid. RNA seqs use U in place of T
         for seq in &seqs {
-            if !seq.chars().all(|c| "ATCG".contains(c)) {
-                panic!("Sequence contains not ACGT bases: {}", seq);
+            if !seq.chars().all(|c| "ATCGU".contains(c)) {
+                return Err(format!("Sequence contains not ACGTU bases: {}", seq));
             }
         }
+        let rna = seqs.iter().any(|s| s.contains('U'));
+        if rna && seqs.iter().any(|s| s.contains('T')) {
+            return Err(format!("Sequences mix T and U: {:?}", seqs));
+        }
         // Encode the sequences
         let mut encoded_seqs: Vec<Vec<usize>> =
-            seqs.iter().map(|s| primaldimer::encode_base(&s)).collect();
+            seqs.iter().map(|s| primaldimer::encode_base(s)).collect();
         // Sort and dedup the sequences
         encoded_seqs.sort_unstable();
         encoded_seqs.dedup();
 
         let encodedseqs = encoded_seqs;
 
-        Kmer { encodedseqs }
+        Ok(Kmer {
+            encodedseqs,
+            start: None,
+            end: None,
+            strand: None,
+            name: None,
+            tail_len: 0,
+            blocked_3p: false,
+            rna,
+        })
+    }
+
+    pub fn acid(&self) -> primaldimer::NucleicAcid {
+        match self.rna {
+            true => primaldimer::NucleicAcid::Rna,
+            false => primaldimer::NucleicAcid::Dna,
+        }
+    }
+
+    pub fn kmer_seqs(&self) -> primaldimer::KmerSeqs<'_> {
+        // The seqs, as scored against other kmers
+        primaldimer::KmerSeqs {
+            seqs: &self.encodedseqs,
+            extendable: !self.blocked_3p,
+            acid: self.acid(),
+            tail_len: self.tail_len,
+        }
     }
 
+    pub fn with_tail(&self, tail: &[usize]) -> Kmer {
+        // A copy with tail added to the 5' end of every seq, in front of any tail_len bases
+        Kmer {
+            encodedseqs: self
+                .encodedseqs
+                .iter()
+                .map(|seq| [tail, seq].concat())
+                .collect(),
+            name: self.name.clone(),
+            tail_len: tail.len() + self.tail_len,
+            ..*self
+        }
+    }
+
+    pub fn site(&self) -> Option<primaldimer::Site> {
+        // The binding site, if all coordinates are known
+        match (self.start, self.end, self.strand) {
+            (Some(start), Some(end), Some(strand)) => {
+                Some(primaldimer::Site { start, end, strand })
+            }
+            _ => None,
+        }
+    }
+}
+#[pymethods]
+impl Kmer {
+    #[new]
+    #[args(
+        start = "None",
+        end = "None",
+        strand = "None",
+        name = "None",
+        tail_len = "0",
+        blocked_3p = "false"
+    )]
+    #[allow(clippy::too_many_arguments)]
+    fn py_new(
+        _idx: usize,
+        seqs: Vec<String>,
+        start: Option<usize>,
+        end: Option<usize>,
+        strand: Option<&str>,
+        name: Option<String>,
+        tail_len: usize,
+        blocked_3p: bool,
+    ) -> PyResult<Self> {
+        let mut kmer = Kmer::try_new(_idx, seqs).map_err(PyValueError::new_err)?;
+        kmer.start = start;
+        kmer.end = end;
+        kmer.name = name;
+        kmer.tail_len = tail_len;
+        kmer.blocked_3p = blocked_3p;
+        kmer.set_strand(strand)?;
+        Ok(kmer)
+    }
+
+    #[getter]
+    pub fn strand(&self) -> Option<String> {
+        // '+' or '-'
+        self.strand.map(|s| s.to_string())
+    }
+
+    #[setter]
+    pub fn set_strand(&mut self, strand: Option<&str>) -> PyResult<()> {
+        self.strand = match strand {
+            Some(s) => Some(s.parse().map_err(PyValueError::new_err)?),
+            None => None,
+        };
+        Ok(())
+    }
+
+    #[allow(clippy::wrong_self_convention)]
     fn into_bytes(&self) -> Vec<Vec<u8>> {
         // Return the sequences in bytes
         self.encodedseqs
@@ -39,10 +162,10 @@ impl Kmer {
 
     #[getter]
     pub fn seqs(&self) -> Vec<String> {
-        // Return the sequences in ATCG format
+        // Return the sequences in ATCG format, or ACGU for RNA
         self.encodedseqs
             .iter()
-            .map(|s| primaldimer::decode_base(s))
+            .map(|s| decode_seq(s, self.acid()))
             .collect()
     }
 
@@ -52,12 +175,174 @@ impl Kmer {
     }
 }
 
-fn do_kmers_interact(kmer1: &Kmer, kmer2: &Kmer, t: f64) -> bool {
-    // Check if two kmers interact
-    for seq1 in &kmer1.encodedseqs {
-        for seq2 in &kmer2.encodedseqs {
-            if primaldimer::does_seq1_extend(&seq1, &seq2, t)
-                | primaldimer::does_seq1_extend(&seq2, &seq1, t)
+#[pyclass]
+#[derive(Clone)]
+pub struct ScoringParams {
+    pub params: primaldimer::ScoringParams,
+}
+#[pymethods]
+impl ScoringParams {
+    #[new]
+    #[args(bonus = "None", logistic = "None")]
+    pub fn new(bonus: Option<Vec<f64>>, logistic: Option<(f64, f64)>) -> PyResult<Self> {
+        // Defaults to the packaged coefficients, which have no logistic.
+        // logistic is the (intercept, slope) from score to dimer probability
+        let mut params = primaldimer::ScoringParams {
+            logistic: logistic.map(|(intercept, slope)| [intercept, slope]),
+            ..Default::default()
+        };
+        if let Some(bonus) = bonus {
+            if bonus.len() != params.bonus.len() {
+                return Err(PyValueError::new_err(format!(
+                    "Expected {} bonus coefficients, got {}",
+                    params.bonus.len(),
+                    bonus.len()
+                )));
+            }
+            params.bonus.copy_from_slice(&bonus);
+        }
+        Ok(ScoringParams { params })
+    }
+
+    #[getter]
+    pub fn bonus(&self) -> Vec<f64> {
+        self.params.bonus.to_vec()
+    }
+
+    #[getter]
+    pub fn logistic(&self) -> Option<(f64, f64)> {
+        self.params
+            .logistic
+            .map(|[intercept, slope]| (intercept, slope))
+    }
+
+    #[staticmethod]
+    pub fn load(path: &str) -> PyResult<Self> {
+        let contents =
+            std::fs::read_to_string(path).map_err(|e| PyIOError::new_err(e.to_string()))?;
+        let params = contents.parse().map_err(PyValueError::new_err)?;
+        Ok(ScoringParams { params })
+    }
+
+    pub fn save(&self, path: &str) -> PyResult<()> {
+        std::fs::write(path, self.params.to_string()).map_err(|e| PyIOError::new_err(e.to_string()))
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "ScoringParams(bonus={:?}, logistic={:?})",
+            self.params.bonus,
+            self.logistic()
+        )
+    }
+}
+
+fn seq_acid(seq: &str) -> PyResult<primaldimer::NucleicAcid> {
+    // NucleicAcid::of, with non ACGTU bases or mixed T and U a ValueError rather than a panic
+    match seq.char_indices().find(|(_i, c)| !"ACGTU".contains(*c)) {
+        Some((index, base)) => Err(PyValueError::new_err(format!(
+            "Invalid base '{}' at index {} of {}",
+            base, index, seq
+        ))),
+        None => primaldimer::NucleicAcid::try_of(seq).map_err(PyValueError::new_err),
+    }
+}
+
+fn encode_seq(seq: &str) -> PyResult<(Vec<usize>, primaldimer::NucleicAcid)> {
+    // encode_base, checked as for seq_acid
+    let acid = seq_acid(seq)?;
+    Ok((primaldimer::encode_base(seq), acid))
+}
+
+fn decode_seq(seq: &[usize], acid: primaldimer::NucleicAcid) -> String {
+    // decode_base, with U in place of T for RNA
+    match acid {
+        primaldimer::NucleicAcid::Rna => primaldimer::decode_base(seq).replace('T', "U"),
+        primaldimer::NucleicAcid::Dna => primaldimer::decode_base(seq),
+    }
+}
+
+fn fitted_logistic(params: &primaldimer::ScoringParams) -> PyResult<[f64; 2]> {
+    // The params' logistic, as probabilities are only defined once one is fitted
+    params.logistic.ok_or_else(|| {
+        PyValueError::new_err(
+            "ScoringParams have no logistic to give a probability. Fit one with fit_scoring_params",
+        )
+    })
+}
+
+fn probability_threshold(p: f64, params: &primaldimer::ScoringParams) -> PyResult<f64> {
+    primaldimer::probability_to_score(&fitted_logistic(params)?, p).ok_or_else(|| {
+        PyValueError::new_err(format!(
+            "Probability must be between 0 and 1 (exclusive): {}",
+            p
+        ))
+    })
+}
+
+fn unwrap_params(params: Option<PyRef<ScoringParams>>) -> primaldimer::ScoringParams {
+    // Use the packaged coefficients if no params are given
+    params
+        .map(|p| p.params)
+        .unwrap_or(primaldimer::DEFAULT_PARAMS)
+}
+
+fn do_kmers_interact(
+    kmer1: &Kmer,
+    kmer2: &Kmer,
+    t: f64,
+    params: &primaldimer::ScoringParams,
+) -> bool {
+    // Check if two kmers interact. Only kmers that aren't 3' blocked can extend,
+    // and tails are weighted by the default TailPolicy
+    let policy = primaldimer::TailPolicy::default();
+    primaldimer::do_kmers_interact(&kmer1.kmer_seqs(), &kmer2.kmer_seqs(), t, &policy, params)
+}
+
+fn kmers_min_score_below(
+    kmer1: &Kmer,
+    kmer2: &Kmer,
+    t: f64,
+    params: &primaldimer::ScoringParams,
+) -> Option<f64> {
+    // The lowest score between any of their seqs, in the directions that can extend, if it is
+    // <= t, otherwise None. Offsets that can't score <= t are skipped
+    let policy = primaldimer::TailPolicy::default();
+    let (kmer1, kmer2) = (&kmer1.kmer_seqs(), &kmer2.kmer_seqs());
+    primaldimer::calc_kmers_min_score_below(kmer1, kmer2, t, &policy, params)
+}
+
+fn kmers_min_score_cached(
+    kmer1: &primaldimer::KmerSeqs,
+    kmer2: &primaldimer::KmerSeqs,
+    policy: &primaldimer::TailPolicy,
+    params: &primaldimer::ScoringParams,
+    cache: &mut primaldimer::PairCache,
+) -> Option<f64> {
+    // calc_kmers_min_score, with each seq pair's min score memoised
+    let mut min_score: Option<f64> = None;
+    for seq1 in kmer1.seqs {
+        for seq2 in kmer2.seqs {
+            if let Some(score) = cache.min_score(kmer1, seq1, kmer2, seq2, policy, params) {
+                min_score = Some(min_score.map_or(score, |m| m.min(score)));
+            }
+        }
+    }
+    min_score
+}
+
+fn do_kmers_interact_cached(
+    kmer1: &primaldimer::KmerSeqs,
+    kmer2: &primaldimer::KmerSeqs,
+    t: f64,
+    policy: &primaldimer::TailPolicy,
+    params: &primaldimer::ScoringParams,
+    cache: &mut primaldimer::PairCache,
+) -> bool {
+    // Same as do_kmers_interact, but each seq pair's min score is memoised
+    for seq1 in kmer1.seqs {
+        for seq2 in kmer2.seqs {
+            if matches!(cache.min_score(kmer1, seq1, kmer2, seq2, policy, params), Some(score) if score <= t)
             {
                 return true;
             }
@@ -66,52 +351,1529 @@ fn do_kmers_interact(kmer1: &Kmer, kmer2: &Kmer, t: f64) -> bool {
     false
 }
 
-#[pyfunction]
+#[pyclass]
+pub struct PairCache {
+    pub cache: primaldimer::PairCache,
+}
+#[pymethods]
+impl PairCache {
+    #[new]
+    #[args(capacity = "100000")]
+    pub fn new(capacity: usize) -> Self {
+        // Holds at most capacity seq pairs, evicting the least recently used
+        PairCache {
+            cache: primaldimer::PairCache::new(capacity),
+        }
+    }
+
+    #[getter]
+    pub fn capacity(&self) -> usize {
+        self.cache.capacity
+    }
+
+    #[getter]
+    pub fn hits(&self) -> u64 {
+        self.cache.hits
+    }
+
+    #[getter]
+    pub fn misses(&self) -> u64 {
+        self.cache.misses
+    }
+
+    pub fn __len__(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn clear(&mut self) {
+        // Drops every entry and resets hits and misses
+        self.cache.clear()
+    }
+
+    pub fn __repr__(&self) -> String {
+        format!(
+            "PairCache(size={}, capacity={}, hits={}, misses={})",
+            self.cache.len(),
+            self.cache.capacity,
+            self.cache.hits,
+            self.cache.misses
+        )
+    }
+}
+
+// Pairs checked between progress callbacks and checks for Ctrl-C
+const PROGRESS_INTERVAL: usize = 1000;
+
+#[pyfunction(params = "None", cache = "None", progress = "None")]
+#[allow(clippy::too_many_arguments)]
 fn which_kmers_pools_interact(
     py: Python<'_>,
     kmers1: Vec<Py<Kmer>>,
     kmers2: Vec<Py<Kmer>>,
     t: f64,
     calc_all: bool,
+    params: Option<PyRef<ScoringParams>>,
+    cache: Option<PyRefMut<PairCache>>,
+    progress: Option<PyObject>,
 ) -> PyResult<Vec<(Py<Kmer>, Py<Kmer>)>> {
+    // progress is called as progress(pairs_checked, total_pairs) every PROGRESS_INTERVAL
+    // pairs and once at the end, including an early return. An exception it raises stops the check.
+    // Each kmer's tail_len bases are weighted by the default TailPolicy
+    pools_interact(
+        py,
+        (kmers1, kmers2),
+        t,
+        calc_all,
+        &[],
+        &primaldimer::TailPolicy::default(),
+        &unwrap_params(params),
+        cache,
+        progress,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn pools_interact(
+    py: Python<'_>,
+    (kmers1, kmers2): (Vec<Py<Kmer>>, Vec<Py<Kmer>>),
+    t: f64,
+    calc_all: bool,
+    shared_tail: &[usize],
+    policy: &primaldimer::TailPolicy,
+    params: &primaldimer::ScoringParams,
+    mut cache: Option<PyRefMut<PairCache>>,
+    progress: Option<PyObject>,
+) -> PyResult<Vec<(Py<Kmer>, Py<Kmer>)>> {
+    // which_kmers_pools_interact, with shared_tail added to the 5' end of every seq
+    let total = kmers1.len() * kmers2.len();
+    let mut checked: usize = 0;
+    // Copies with the shared tail. Without one, each pair is borrowed as it is checked
+    let tailed = |kmers: &[Py<Kmer>]| -> Vec<Kmer> {
+        kmers
+            .iter()
+            .map(|kmer| kmer.as_ref(py).borrow().with_tail(shared_tail))
+            .collect()
+    };
+    let tailed = (!shared_tail.is_empty()).then(|| (tailed(&kmers1), tailed(&kmers2)));
+
     // Interaction tuples
     let mut interacting_kmers: Vec<(Py<Kmer>, Py<Kmer>)> = Vec::new();
 
     // Check if two pools of kmers interact
-    for kmer1 in &kmers1 {
-        for kmer2 in &kmers2 {
-            if do_kmers_interact(&kmer1.as_ref(py).borrow(), &kmer2.as_ref(py).borrow(), t) {
+    for (i, kmer1) in kmers1.iter().enumerate() {
+        for (j, kmer2) in kmers2.iter().enumerate() {
+            // The borrows end before any progress callback
+            let interacts = {
+                let (borrowed1, borrowed2);
+                let (k1, k2) = match &tailed {
+                    Some((tailed1, tailed2)) => (&tailed1[i], &tailed2[j]),
+                    None => {
+                        borrowed1 = kmer1.as_ref(py).borrow();
+                        borrowed2 = kmer2.as_ref(py).borrow();
+                        (&*borrowed1, &*borrowed2)
+                    }
+                };
+                let (k1, k2) = (&k1.kmer_seqs(), &k2.kmer_seqs());
+                match cache.as_mut() {
+                    Some(c) => do_kmers_interact_cached(k1, k2, t, policy, params, &mut c.cache),
+                    None => primaldimer::do_kmers_interact(k1, k2, t, policy, params),
+                }
+            };
+            checked += 1;
+            if interacts {
                 interacting_kmers.push((kmer1.clone(), kmer2.clone()));
                 // Early return if we only want to know if any interact
                 if !calc_all {
+                    if let Some(callback) = &progress {
+                        callback.call1(py, (checked, total))?;
+                    }
                     return Ok(interacting_kmers);
                 }
             }
+
+            if checked % PROGRESS_INTERVAL == 0 {
+                // Raises KeyboardInterrupt on Ctrl-C
+                py.check_signals()?;
+                if let Some(callback) = &progress {
+                    callback.call1(py, (checked, total))?;
+                }
+            }
         }
     }
-    return Ok(interacting_kmers);
+    if let Some(callback) = &progress {
+        callback.call1(py, (checked, total))?;
+    }
+    Ok(interacting_kmers)
 }
 
-#[pyfunction]
-fn calc_at_offset_py(seq1: &str, seq2: &str, offset: i32) -> f64 {
+#[pyfunction(params = "None", cache = "None", progress = "None")]
+#[allow(clippy::too_many_arguments)]
+fn which_kmers_pools_interact_prob(
+    py: Python<'_>,
+    kmers1: Vec<Py<Kmer>>,
+    kmers2: Vec<Py<Kmer>>,
+    p: f64,
+    calc_all: bool,
+    params: Option<PyRef<ScoringParams>>,
+    cache: Option<PyRefMut<PairCache>>,
+    progress: Option<PyObject>,
+) -> PyResult<Vec<(Py<Kmer>, Py<Kmer>)>> {
+    // Kmers interact if the dimer probability from the params' logistic is >= p
+    let t = probability_threshold(
+        p,
+        params
+            .as_ref()
+            .map_or(&primaldimer::DEFAULT_PARAMS, |p| &p.params),
+    )?;
+    which_kmers_pools_interact(py, kmers1, kmers2, t, calc_all, params, cache, progress)
+}
+
+#[pyfunction(
+    tail = "None",
+    tail_tail = "0.",
+    tail_primer = "1.",
+    params = "None",
+    cache = "None",
+    progress = "None"
+)]
+#[allow(clippy::too_many_arguments)]
+fn which_kmers_pools_interact_tailed(
+    py: Python<'_>,
+    kmers1: Vec<Py<Kmer>>,
+    kmers2: Vec<Py<Kmer>>,
+    t: f64,
+    calc_all: bool,
+    tail: Option<&str>,
+    tail_tail: f64,
+    tail_primer: f64,
+    params: Option<PyRef<ScoringParams>>,
+    cache: Option<PyRefMut<PairCache>>,
+    progress: Option<PyObject>,
+) -> PyResult<Vec<(Py<Kmer>, Py<Kmer>)>> {
+    // which_kmers_pools_interact, where each kmer's first tail_len bases are a 5' tail.
+    // A shared tail is added to the 5' end of every seq, in front of any tail_len bases,
+    // and takes the acid of the kmer, so it can be written with T or U.
+    // Positions where both or one base is in a tail are weighted by tail_tail and
+    // tail_primer, between 0 and 1. 0 masks them, 1 scores them as ordinary seq
+    let policy =
+        primaldimer::TailPolicy::new(tail_tail, tail_primer).map_err(PyValueError::new_err)?;
+    let shared_tail = match tail {
+        Some(tail) if !tail.chars().all(|c| "ACGTU".contains(c)) => {
+            return Err(PyValueError::new_err(format!(
+                "Tail contains not ACGTU bases: {}",
+                tail
+            )))
+        }
+        Some(tail) => primaldimer::encode_base(tail),
+        None => Vec::new(),
+    };
+    pools_interact(
+        py,
+        (kmers1, kmers2),
+        t,
+        calc_all,
+        &shared_tail,
+        &policy,
+        &unwrap_params(params),
+        cache,
+        progress,
+    )
+}
+
+// (kmer1, kmer2, lowest score)
+type ScoredPair = (Py<Kmer>, Py<Kmer>, f64);
+
+#[pyclass]
+pub struct InteractingPairs {
+    kmers1: Vec<Py<Kmer>>,
+    kmers2: Vec<Py<Kmer>>,
+    t: f64,
+    params: primaldimer::ScoringParams,
+    cache: Option<Py<PairCache>>,
+    progress: Option<PyObject>,
+    // Index of the next pair to check, as kmers1 index * kmers2.len() + kmers2 index
+    next_pair: usize,
+    // If the final progress call has been made
+    finished: bool,
+}
+#[pymethods]
+impl InteractingPairs {
+    pub fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    pub fn __next__(mut slf: PyRefMut<Self>, py: Python<'_>) -> PyResult<Option<ScoredPair>> {
+        // Checks pairs until the next interacting one, which is returned with its lowest score
+        let total = slf.kmers1.len() * slf.kmers2.len();
+        while slf.next_pair < total {
+            let (i, j) = (
+                slf.next_pair / slf.kmers2.len(),
+                slf.next_pair % slf.kmers2.len(),
+            );
+            slf.next_pair += 1;
+            if slf.next_pair % PROGRESS_INTERVAL == 0 {
+                py.check_signals()?;
+                if let Some(callback) = &slf.progress {
+                    callback.call1(py, (slf.next_pair, total))?;
+                }
+            }
+
+            let (kmer1, kmer2) = (slf.kmers1[i].clone(), slf.kmers2[j].clone());
+            let (k1, k2) = (&kmer1.as_ref(py).borrow(), &kmer2.as_ref(py).borrow());
+            let score = match &slf.cache {
+                Some(c) => {
+                    let cache = &mut c.as_ref(py).borrow_mut().cache;
+                    let policy = primaldimer::TailPolicy::default();
+                    let (k1, k2) = (&k1.kmer_seqs(), &k2.kmer_seqs());
+                    kmers_min_score_cached(k1, k2, &policy, &slf.params, cache)
+                        .filter(|s| *s <= slf.t)
+                }
+                None => kmers_min_score_below(k1, k2, slf.t, &slf.params),
+            };
+            if let Some(score) = score {
+                return Ok(Some((kmer1.clone(), kmer2.clone(), score)));
+            }
+        }
+        if !slf.finished {
+            slf.finished = true;
+            if let Some(callback) = &slf.progress {
+                callback.call1(py, (slf.next_pair, total))?;
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[pyfunction(params = "None", cache = "None", progress = "None")]
+fn iter_interacting_kmers(
+    kmers1: Vec<Py<Kmer>>,
+    kmers2: Vec<Py<Kmer>>,
+    t: f64,
+    params: Option<PyRef<ScoringParams>>,
+    cache: Option<Py<PairCache>>,
+    progress: Option<PyObject>,
+) -> InteractingPairs {
+    // A lazy version of which_kmers_pools_interact with calc_all=True.
+    // Yields (kmer1, kmer2, lowest score) as each interacting pair is found.
+    // cache and progress are as for which_kmers_pools_interact
+    InteractingPairs {
+        kmers1,
+        kmers2,
+        t,
+        params: unwrap_params(params),
+        cache,
+        progress,
+        next_pair: 0,
+        finished: false,
+    }
+}
+
+#[pyfunction(
+    params = "None",
+    tail_len1 = "0",
+    tail_len2 = "0",
+    tail_tail = "0.",
+    tail_primer = "1."
+)]
+#[allow(clippy::too_many_arguments)]
+fn calc_at_offset_py(
+    seq1: &str,
+    seq2: &str,
+    offset: i32,
+    params: Option<PyRef<ScoringParams>>,
+    tail_len1: usize,
+    tail_len2: usize,
+    tail_tail: f64,
+    tail_primer: f64,
+) -> PyResult<f64> {
     //Provide strings in 5'-3'
     // This will return the score for this offset
-    let seq1 = primaldimer::encode_base(seq1);
-    let mut seq2 = primaldimer::encode_base(seq2);
+    // Seqs containing U are scored as RNA.
+    // The first tail_len1 and tail_len2 bases of each seq are 5' tails, weighted by
+    // tail_tail and tail_primer as for which_kmers_pools_interact_tailed
+    let policy =
+        primaldimer::TailPolicy::new(tail_tail, tail_primer).map_err(PyValueError::new_err)?;
+    let (seq1, acid1) = encode_seq(seq1)?;
+    let (mut seq2, acid2) = encode_seq(seq2)?;
+    seq2.reverse();
+    let acids = (acid1, acid2);
+
+    match primaldimer::try_calc_at_offset(
+        &seq1,
+        &seq2,
+        offset,
+        acids,
+        (tail_len1, tail_len2),
+        &policy,
+        &unwrap_params(params),
+    ) {
+        Ok(score) => Ok(score.unwrap_or(100.)),
+        Err(e) => Err(PyValueError::new_err(e.to_string())),
+    }
+}
+// (seq1_index, seq2_index, is_match, nn_score)
+type PositionTuple = (usize, usize, bool, Option<f64>);
+
+#[pyclass]
+pub struct ScoreBreakdown {
+    #[pyo3(get)]
+    pub score: f64,
+    #[pyo3(get)]
+    pub dangling_ends: f64,
+    #[pyo3(get)]
+    pub extension: f64,
+    #[pyo3(get)]
+    pub match_prop: f64,
+    #[pyo3(get)]
+    pub longest_match: f64,
+    #[pyo3(get)]
+    pub bubbles: f64,
+    #[pyo3(get)]
+    pub nn_stack: f64,
+    #[pyo3(get)]
+    pub positions: Option<Vec<PositionTuple>>,
+}
+
+impl From<primaldimer::ScoreBreakdown> for ScoreBreakdown {
+    fn from(breakdown: primaldimer::ScoreBreakdown) -> Self {
+        ScoreBreakdown {
+            score: breakdown.score,
+            dangling_ends: breakdown.dangling_ends,
+            extension: breakdown.extension,
+            match_prop: breakdown.match_prop,
+            longest_match: breakdown.longest_match,
+            bubbles: breakdown.bubbles,
+            nn_stack: breakdown.nn_stack,
+            positions: breakdown.positions.map(|positions| {
+                positions
+                    .iter()
+                    .map(|p| (p.seq1_index, p.seq2_index, p.is_match, p.nn_score))
+                    .collect()
+            }),
+        }
+    }
+}
+
+#[pyfunction(per_position = "false", params = "None")]
+fn calc_at_offset_breakdown_py(
+    seq1: &str,
+    seq2: &str,
+    offset: i32,
+    per_position: bool,
+    params: Option<PyRef<ScoringParams>>,
+) -> PyResult<Option<ScoreBreakdown>> {
+    //Provide strings in 5'-3'. Seqs containing U are scored as RNA
+    // This will return each term of the score for this offset, or None if seq1 can't extend
+    let (seq1, acid1) = encode_seq(seq1)?;
+    let (mut seq2, acid2) = encode_seq(seq2)?;
     seq2.reverse();
 
-    match primaldimer::calc_at_offset(&seq1, &seq2, offset) {
-        Some(score) => return score,
-        None => return 100.,
+    Ok(primaldimer::calc_at_offset_breakdown(
+        &seq1,
+        &seq2,
+        offset,
+        (acid1, acid2),
+        per_position,
+        &unwrap_params(params),
+    )
+    .map(ScoreBreakdown::from))
+}
+#[pyfunction(params = "None")]
+fn do_seqs_interact_py(
+    seq1: &str,
+    seq2: &str,
+    t: f64,
+    params: Option<PyRef<ScoringParams>>,
+) -> PyResult<bool> {
+    // Seqs containing U are scored as RNA
+    seq_acid(seq1)?;
+    seq_acid(seq2)?;
+    Ok(primaldimer::do_seqs_interact(
+        seq1,
+        seq2,
+        t,
+        &unwrap_params(params),
+    ))
+}
+#[pyfunction(params = "None")]
+fn do_pools_interact_py(
+    pool1: Vec<&str>,
+    pool2: Vec<&str>,
+    t: f64,
+    params: Option<PyRef<ScoringParams>>,
+) -> PyResult<bool> {
+    // Seqs containing U are scored as RNA
+    for seq in pool1.iter().chain(pool2.iter()) {
+        seq_acid(seq)?;
+    }
+    Ok(primaldimer::do_pools_interact(
+        pool1,
+        pool2,
+        t,
+        &unwrap_params(params),
+    ))
+}
+
+#[pyfunction(params = "None")]
+fn do_pools_interact_prob_py(
+    pool1: Vec<&str>,
+    pool2: Vec<&str>,
+    p: f64,
+    params: Option<PyRef<ScoringParams>>,
+) -> PyResult<bool> {
+    for seq in pool1.iter().chain(pool2.iter()) {
+        seq_acid(seq)?;
+    }
+    let params = unwrap_params(params);
+    Ok(primaldimer::do_pools_interact(
+        pool1,
+        pool2,
+        probability_threshold(p, &params)?,
+        &params,
+    ))
+}
+#[pyfunction(params = "None")]
+fn calc_seqs_score_py(
+    seq1: &str,
+    seq2: &str,
+    params: Option<PyRef<ScoringParams>>,
+) -> PyResult<Option<(f64, Option<f64>)>> {
+    // Provide strings in 5'-3'. Seqs containing U are scored as RNA
+    // Returns the lowest score across all offsets and its probability from the params' logistic,
+    // or None for the probability if the params have no logistic
+    let (s1, acid1) = encode_seq(seq1)?;
+    let (s2, acid2) = encode_seq(seq2)?;
+    let acids = (acid1, acid2);
+    let params = unwrap_params(params);
+
+    Ok(
+        primaldimer::calc_seqs_min_score_acids(&s1, &s2, acids, (true, true), &params).map(
+            |score| {
+                let p = params
+                    .logistic
+                    .map(|coefs| primaldimer::logistic(&coefs, score));
+                (score, p)
+            },
+        ),
+    )
+}
+
+#[pyfunction(rna_template = "false")]
+fn calc_tm_py(seq: &str, rna_template: bool) -> PyResult<f64> {
+    // Provide a DNA string in 5'-3'
+    // The melting temperature (C) against its perfect complement, which is RNA if rna_template
+    if seq.len() < 2 || !seq.chars().all(|c| "ACGT".contains(c)) {
+        return Err(PyValueError::new_err(format!(
+            "Tm needs a DNA seq of at least 2 bases: {}",
+            seq
+        )));
+    }
+    let encoded = primaldimer::encode_base(seq);
+    Ok(match rna_template {
+        true => primaldimer::calc_tm_hybrid(&encoded),
+        false => primaldimer::calc_tm(&encoded),
+    })
+}
+#[pyfunction(params = "None")]
+fn score_to_probability_py(score: f64, params: Option<PyRef<ScoringParams>>) -> PyResult<f64> {
+    // The dimer probability of a score under the params' fitted logistic
+    let coefs = fitted_logistic(&unwrap_params(params))?;
+    Ok(primaldimer::logistic(&coefs, score))
+}
+#[pyfunction(params = "None")]
+fn probability_to_score_py(p: f64, params: Option<PyRef<ScoringParams>>) -> PyResult<f64> {
+    probability_threshold(p, &unwrap_params(params))
+}
+
+fn encode_pairs(pairs: &[(&str, &str)]) -> PyResult<Vec<primaldimer::EncodedPair>> {
+    // encode_seq of each seq, so pairs containing RNA are scored as such
+    pairs
+        .iter()
+        .map(|(s1, s2)| {
+            let ((s1, acid1), (s2, acid2)) = (encode_seq(s1)?, encode_seq(s2)?);
+            Ok((s1, s2, (acid1, acid2)))
+        })
+        .collect()
+}
+
+#[pyclass]
+pub struct FittedParams {
+    // With the logistic from score to dimer probability fitted alongside them
+    #[pyo3(get)]
+    pub params: ScoringParams,
+    #[pyo3(get)]
+    pub log_loss: f64,
+}
+#[pymethods]
+impl FittedParams {
+    fn probability(&self, score: f64) -> PyResult<f64> {
+        // The dimer probability of a score under the fitted logistic
+        let coefs = fitted_logistic(&self.params.params)?;
+        Ok(primaldimer::logistic(&coefs, score))
+    }
+}
+
+#[pyfunction(initial = "None", max_iter = "100")]
+fn fit_scoring_params_py(
+    pairs: Vec<(&str, &str)>,
+    labels: Vec<bool>,
+    initial: Option<PyRef<ScoringParams>>,
+    max_iter: usize,
+) -> PyResult<FittedParams> {
+    // Provide pairs of 5'-3' strings, with labels of whether they formed a dimer
+    if pairs.len() != labels.len() {
+        return Err(PyValueError::new_err(format!(
+            "Number of pairs ({}) and labels ({}) differ",
+            pairs.len(),
+            labels.len()
+        )));
+    }
+    let encoded_pairs = encode_pairs(&pairs)?;
+
+    let fitted =
+        primaldimer::fit_params(&encoded_pairs, &labels, &unwrap_params(initial), max_iter);
+    Ok(FittedParams {
+        params: ScoringParams {
+            params: fitted.params,
+        },
+        log_loss: fitted.log_loss,
+    })
+}
+
+#[pyclass]
+pub struct ThresholdEvaluation {
+    #[pyo3(get)]
+    pub scores: Vec<Option<f64>>,
+    #[pyo3(get)]
+    pub thresholds: Vec<f64>,
+    #[pyo3(get)]
+    pub fpr: Vec<f64>,
+    #[pyo3(get)]
+    pub tpr: Vec<f64>,
+    #[pyo3(get)]
+    pub precision: Vec<f64>,
+    #[pyo3(get)]
+    pub roc_auc: f64,
+    #[pyo3(get)]
+    pub pr_auc: f64,
+    #[pyo3(get)]
+    pub target_fpr: f64,
+    // None if no threshold reaches target_fpr
+    #[pyo3(get)]
+    pub recommended_threshold: Option<f64>,
+}
+#[pymethods]
+impl ThresholdEvaluation {
+    #[getter]
+    pub fn recall(&self) -> Vec<f64> {
+        self.tpr.clone()
+    }
+}
+
+#[pyfunction(target_fpr = "0.05", params = "None")]
+fn evaluate_thresholds_py(
+    pairs: Vec<(&str, &str)>,
+    labels: Vec<bool>,
+    target_fpr: f64,
+    params: Option<PyRef<ScoringParams>>,
+) -> PyResult<ThresholdEvaluation> {
+    // Provide pairs of 5'-3' strings, with labels of whether they formed a dimer
+    if pairs.len() != labels.len() {
+        return Err(PyValueError::new_err(format!(
+            "Number of pairs ({}) and labels ({}) differ",
+            pairs.len(),
+            labels.len()
+        )));
+    }
+    if labels.iter().all(|l| *l) || labels.iter().all(|l| !*l) {
+        return Err(PyValueError::new_err(
+            "Both positive and negative labels are required",
+        ));
+    }
+    let eval =
+        primaldimer::evaluate_thresholds(&encode_pairs(&pairs)?, &labels, &unwrap_params(params));
+    let recommended_threshold = eval.threshold_for_fpr(target_fpr);
+
+    Ok(ThresholdEvaluation {
+        scores: eval.scores,
+        thresholds: eval.thresholds,
+        fpr: eval.fpr,
+        tpr: eval.tpr,
+        precision: eval.precision,
+        roc_auc: eval.roc_auc,
+        pr_auc: eval.pr_auc,
+        target_fpr,
+        recommended_threshold,
+    })
+}
+
+#[pyclass]
+pub struct Significance {
+    // None if the seqs can't extend
+    #[pyo3(get)]
+    pub score: Option<f64>,
+    // Fraction of background pairs scoring <= score, with a pseudocount
+    #[pyo3(get)]
+    pub p_value: f64,
+    // None if there is no score, or the background has no spread
+    #[pyo3(get)]
+    pub z_score: Option<f64>,
+}
+
+#[pyclass]
+pub struct BackgroundModel {
+    pub model: primaldimer::BackgroundModel,
+}
+#[pymethods]
+impl BackgroundModel {
+    #[new]
+    #[args(n_samples = "1000", seed = "0", params = "None")]
+    pub fn new(n_samples: usize, seed: u64, params: Option<PyRef<ScoringParams>>) -> Self {
+        BackgroundModel {
+            model: primaldimer::BackgroundModel::new(n_samples, seed, unwrap_params(params)),
+        }
+    }
+
+    pub fn significance(&mut self, seq1: &str, seq2: &str) -> PyResult<Significance> {
+        // Provide strings in 5'-3'. Seqs containing U are scored as RNA
+        // The score against length, GC and acid matched random pairs
+        let (s1, acid1) = encode_seq(seq1)?;
+        let (s2, acid2) = encode_seq(seq2)?;
+
+        let sig = self.model.significance(&s1, &s2, (acid1, acid2));
+        Ok(Significance {
+            score: sig.score,
+            p_value: sig.p_value,
+            z_score: sig.z_score,
+        })
+    }
+
+    #[args(rna1 = "false", rna2 = "false")]
+    pub fn distribution(
+        &mut self,
+        len1: usize,
+        gc1: usize,
+        len2: usize,
+        gc2: usize,
+        rna1: bool,
+        rna2: bool,
+    ) -> PyResult<Vec<Option<f64>>> {
+        // The background scores for sequences of these lengths and GC counts, scored as RNA
+        // if rna1 or rna2
+        for (len, gc) in [(len1, gc1), (len2, gc2)] {
+            if gc > len {
+                return Err(PyValueError::new_err(format!(
+                    "GC count ({}) is greater than the length ({})",
+                    gc, len
+                )));
+            }
+        }
+        let acid = |rna| match rna {
+            true => primaldimer::NucleicAcid::Rna,
+            false => primaldimer::NucleicAcid::Dna,
+        };
+        let acids = (acid(rna1), acid(rna2));
+        Ok(self
+            .model
+            .distribution((len1, gc1), (len2, gc2), acids)
+            .clone())
+    }
+
+    #[getter]
+    pub fn cache_size(&self) -> usize {
+        self.model.cache_size()
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.model.clear_cache()
+    }
+}
+
+#[pyclass]
+pub struct DimerProduct {
+    #[pyo3(get)]
+    pub offset: i32,
+    #[pyo3(get)]
+    pub score: f64,
+    // Each strand 5'-3' after extension
+    #[pyo3(get)]
+    pub seq1_strand: String,
+    #[pyo3(get)]
+    pub seq2_strand: String,
+    #[pyo3(get)]
+    pub seq1_extends: bool,
+    #[pyo3(get)]
+    pub seq2_extends: bool,
+    #[pyo3(get)]
+    pub length: usize,
+}
+#[pymethods]
+impl DimerProduct {
+    #[getter]
+    pub fn both_extendable(&self) -> bool {
+        self.seq1_extends && self.seq2_extends
+    }
+}
+
+#[pyfunction(params = "None")]
+fn predict_dimer_product_py(
+    seq1: &str,
+    seq2: &str,
+    params: Option<PyRef<ScoringParams>>,
+) -> PyResult<Option<DimerProduct>> {
+    // Provide strings in 5'-3'. Seqs containing U are scored as RNA
+    // Returns the product of the lowest scoring dimer, or None if neither seq can extend
+    let (s1, acid1) = encode_seq(seq1)?;
+    let (s2, acid2) = encode_seq(seq2)?;
+    // The extension is DNA, whatever the seq it extends
+    let decode_strand = |strand: &[usize], len: usize, acid| {
+        decode_seq(&strand[..len], acid) + &primaldimer::decode_base(&strand[len..])
     };
+
+    let product =
+        primaldimer::predict_dimer_product(&s1, &s2, (acid1, acid2), &unwrap_params(params));
+    Ok(product.map(|product| DimerProduct {
+        offset: product.offset,
+        score: product.score,
+        seq1_strand: decode_strand(&product.seq1_strand, s1.len(), acid1),
+        seq2_strand: decode_strand(&product.seq2_strand, s2.len(), acid2),
+        seq1_extends: product.seq1_extends,
+        seq2_extends: product.seq2_extends,
+        length: product.length,
+    }))
 }
+
 #[pyfunction]
-fn do_seqs_interact_py(seq1: &str, seq2: &str, t: f64) -> bool {
-    return primaldimer::do_seqs_interact(seq1, seq2, t);
+fn which_kmers_short_products(
+    py: Python<'_>,
+    kmers: Vec<Py<Kmer>>,
+    max_len: usize,
+) -> Vec<(Py<Kmer>, Py<Kmer>, usize)> {
+    // Returns (forward, reverse, product length) for every pair in the pool that would
+    // amplify a product shorter than max_len. Kmers without coordinates and strand,
+    // or that are 3' blocked, are skipped
+    let (sites, site_kmers): (Vec<primaldimer::Site>, Vec<&Py<Kmer>>) = kmers
+        .iter()
+        .filter_map(|kmer| {
+            let kmer_ref = kmer.as_ref(py).borrow();
+            match kmer_ref.blocked_3p {
+                true => None,
+                false => kmer_ref.site().map(|site| (site, kmer)),
+            }
+        })
+        .unzip();
+
+    primaldimer::find_short_products(&sites, max_len)
+        .into_iter()
+        .map(|(f, r, len)| (site_kmers[f].clone(), site_kmers[r].clone(), len))
+        .collect()
 }
-#[pyfunction]
-fn do_pools_interact_py(pool1: Vec<&str>, pool2: Vec<&str>, t: f64) -> bool {
-    return primaldimer::do_pools_interact(pool1, pool2, t);
+
+#[pyclass]
+pub struct PoolCheck {
+    #[pyo3(get)]
+    pub dimers: Vec<(Py<Kmer>, Py<Kmer>)>,
+    #[pyo3(get)]
+    pub short_products: Vec<(Py<Kmer>, Py<Kmer>, usize)>,
+}
+
+#[pyfunction(params = "None")]
+fn check_pool(
+    py: Python<'_>,
+    kmers: Vec<Py<Kmer>>,
+    t: f64,
+    max_product_len: usize,
+    params: Option<PyRef<ScoringParams>>,
+) -> PoolCheck {
+    // Checks a single pool for both dimers and short products
+    let params = unwrap_params(params);
+
+    // Each unordered pair, including each kmer with itself, is checked once
+    let mut dimers = Vec::new();
+    for (i, kmer1) in kmers.iter().enumerate() {
+        for kmer2 in kmers[i..].iter() {
+            if do_kmers_interact(
+                &kmer1.as_ref(py).borrow(),
+                &kmer2.as_ref(py).borrow(),
+                t,
+                &params,
+            ) {
+                dimers.push((kmer1.clone(), kmer2.clone()));
+            }
+        }
+    }
+    let short_products = which_kmers_short_products(py, kmers, max_product_len);
+
+    PoolCheck {
+        dimers,
+        short_products,
+    }
+}
+
+#[pyclass]
+#[derive(Clone)]
+pub struct BindingSite {
+    #[pyo3(get)]
+    pub kmer: Py<Kmer>,
+    // The sequence in the kmer that binds, 5'-3'
+    #[pyo3(get)]
+    pub seq: String,
+    #[pyo3(get)]
+    pub chrom: String,
+    #[pyo3(get)]
+    pub start: usize,
+    #[pyo3(get)]
+    pub end: usize,
+    pub strand: primaldimer::Strand,
+    #[pyo3(get)]
+    pub score: f64,
+    // The reference on the primer's strand, 5'-3'
+    #[pyo3(get)]
+    pub target: String,
+    #[pyo3(get)]
+    pub alignment: String,
+    // If the site is the kmer's own coordinates
+    #[pyo3(get)]
+    pub on_target: bool,
+}
+
+impl BindingSite {
+    fn new(
+        kmer: &Py<Kmer>,
+        seq: &[usize],
+        chrom: &str,
+        site: &primaldimer::BindingSite,
+        kmer_site: Option<primaldimer::Site>,
+    ) -> Self {
+        BindingSite {
+            kmer: kmer.clone(),
+            seq: primaldimer::decode_base(seq),
+            chrom: chrom.to_string(),
+            start: site.site.start,
+            end: site.site.end,
+            strand: site.site.strand,
+            score: site.score,
+            target: primaldimer::decode_base(&site.target),
+            alignment: primaldimer::format_alignment(seq, &site.target),
+            on_target: kmer_site == Some(site.site),
+        }
+    }
+
+    pub fn site(&self) -> primaldimer::Site {
+        primaldimer::Site {
+            start: self.start,
+            end: self.end,
+            strand: self.strand,
+        }
+    }
+}
+#[pymethods]
+impl BindingSite {
+    #[getter]
+    pub fn strand(&self) -> String {
+        // '+' or '-'
+        self.strand.to_string()
+    }
+}
+
+fn scan_kmers(
+    py: Python<'_>,
+    kmers: &[Py<Kmer>],
+    records: &[primaldimer::FastaRecord],
+    reference_acid: primaldimer::NucleicAcid,
+    t: f64,
+    params: &primaldimer::ScoringParams,
+) -> Vec<BindingSite> {
+    // Scans every sequence of every kmer against every record
+    let mut binding_sites = Vec::new();
+    for kmer in kmers {
+        let kmer_ref = kmer.as_ref(py).borrow();
+        let kmer_site = kmer_ref.site();
+        let acids = (kmer_ref.acid(), reference_acid);
+
+        for seq in kmer_ref.encodedseqs.iter() {
+            for record in records {
+                for site in primaldimer::scan_reference_acids(seq, &record.seq, acids, t, params) {
+                    binding_sites.push(BindingSite::new(kmer, seq, &record.name, &site, kmer_site));
+                }
+            }
+        }
+    }
+    binding_sites
+}
+
+#[pyfunction(include_on_target = "false", rna_reference = "false", params = "None")]
+fn scan_kmers_reference(
+    py: Python<'_>,
+    kmers: Vec<Py<Kmer>>,
+    fasta_path: &str,
+    t: f64,
+    include_on_target: bool,
+    rna_reference: bool,
+    params: Option<PyRef<ScoringParams>>,
+) -> PyResult<Vec<BindingSite>> {
+    // Reports every site on either strand of the reference where a kmer binds with a score <= t.
+    // With rna_reference, kmers binding the reference itself (reverse strand sites) are scored
+    // as binding RNA, as an RT primer does. The other strand is its cDNA.
+    // Sites need a base past their 3' end to extend onto, so none end at a record's edge
+    let records =
+        primaldimer::read_fasta(fasta_path).map_err(|e| PyIOError::new_err(e.to_string()))?;
+    let reference_acid = match rna_reference {
+        true => primaldimer::NucleicAcid::Rna,
+        false => primaldimer::NucleicAcid::Dna,
+    };
+
+    let mut binding_sites = scan_kmers(
+        py,
+        &kmers,
+        &records,
+        reference_acid,
+        t,
+        &unwrap_params(params),
+    );
+    if !include_on_target {
+        binding_sites.retain(|site| !site.on_target);
+    }
+    Ok(binding_sites)
+}
+
+#[pyclass]
+pub struct Amplicon {
+    #[pyo3(get)]
+    pub chrom: String,
+    #[pyo3(get)]
+    pub start: usize,
+    #[pyo3(get)]
+    pub end: usize,
+    #[pyo3(get)]
+    pub length: usize,
+    #[pyo3(get)]
+    pub forward: BindingSite,
+    #[pyo3(get)]
+    pub reverse: BindingSite,
+}
+#[pymethods]
+impl Amplicon {
+    #[getter]
+    pub fn intended(&self) -> bool {
+        // Both primers bind at their own coordinates
+        self.forward.on_target && self.reverse.on_target
+    }
+}
+
+#[pyfunction(params = "None")]
+fn in_silico_pcr(
+    py: Python<'_>,
+    kmers: Vec<Py<Kmer>>,
+    fasta_path: &str,
+    t: f64,
+    max_product_len: usize,
+    params: Option<PyRef<ScoringParams>>,
+) -> PyResult<Vec<Amplicon>> {
+    // Predicts every product the pool would make on the reference, intended and off-target.
+    // Any site binding the forward strand pairs with any downstream site binding the reverse.
+    // 3' blocked kmers can't prime, so are skipped
+    let kmers: Vec<Py<Kmer>> = kmers
+        .into_iter()
+        .filter(|kmer| !kmer.as_ref(py).borrow().blocked_3p)
+        .collect();
+    let records =
+        primaldimer::read_fasta(fasta_path).map_err(|e| PyIOError::new_err(e.to_string()))?;
+    let params = unwrap_params(params);
+
+    // Every seq of every kmer is a primer, with its acid and the kmer it came from
+    let kmer_refs: Vec<PyRef<Kmer>> = kmers.iter().map(|k| k.as_ref(py).borrow()).collect();
+    let mut primers: Vec<(&[usize], primaldimer::NucleicAcid)> = Vec::new();
+    let mut owners: Vec<usize> = Vec::new();
+    for (index, kmer) in kmer_refs.iter().enumerate() {
+        for seq in kmer.encodedseqs.iter() {
+            primers.push((seq, kmer.acid()));
+            owners.push(index);
+        }
+    }
+    let binding_site = |chrom: &str, (primer, site): &(usize, primaldimer::BindingSite)| {
+        let kmer = owners[*primer];
+        BindingSite::new(
+            &kmers[kmer],
+            primers[*primer].0,
+            chrom,
+            site,
+            kmer_refs[kmer].site(),
+        )
+    };
+
+    let mut amplicons = Vec::new();
+    for record in records.iter() {
+        // PCR amplifies DNA
+        for product in
+            primaldimer::in_silico_pcr(&primers, &record.seq, t, max_product_len, &params)
+        {
+            amplicons.push(Amplicon {
+                chrom: record.name.clone(),
+                start: product.forward.1.site.start,
+                end: product.reverse.1.site.end,
+                length: product.length,
+                forward: binding_site(&record.name, &product.forward),
+                reverse: binding_site(&record.name, &product.reverse),
+            });
+        }
+    }
+    Ok(amplicons)
+}
+
+#[pyclass]
+pub struct GenomeBinding {
+    #[pyo3(get)]
+    pub kmer: Py<Kmer>,
+    #[pyo3(get)]
+    pub genome: String,
+    // The best binding seq in the kmer, and its site. None if no seq could be placed.
+    // If none binds near a kmer's coordinates it is placed there, scored inf if it can't extend
+    #[pyo3(get)]
+    pub site: Option<BindingSite>,
+    // Mismatch positions, counted from the 3' base (0)
+    #[pyo3(get)]
+    pub mismatches: Vec<usize>,
+    #[pyo3(get)]
+    pub extension_score: Option<f64>,
+    // False flags a genome where no seq in the kmer still binds
+    #[pyo3(get)]
+    pub binds: bool,
+}
+
+#[pyfunction(padding = "500", params = "None")]
+fn kmers_genome_binding(
+    py: Python<'_>,
+    kmers: Vec<Py<Kmer>>,
+    fasta_path: &str,
+    t: f64,
+    padding: usize,
+    params: Option<PyRef<ScoringParams>>,
+) -> PyResult<Vec<GenomeBinding>> {
+    // For each kmer and genome (or aligned record), reports the best binding site and its mismatches.
+    // Kmers with coordinates are searched within padding of their site, others across both strands.
+    // Alignment gaps are removed, so aligned records are searched as their genomes
+    let records =
+        primaldimer::read_alignment(fasta_path).map_err(|e| PyIOError::new_err(e.to_string()))?;
+    let params = unwrap_params(params);
+
+    let mut bindings = Vec::new();
+    for kmer in kmers.iter() {
+        let kmer_ref = kmer.as_ref(py).borrow();
+        let kmer_site = kmer_ref.site();
+
+        for record in records.iter() {
+            let binding = primaldimer::calc_genome_binding(
+                &kmer_ref.encodedseqs,
+                kmer_ref.acid(),
+                &record.seq,
+                kmer_site,
+                padding,
+                t,
+                &params,
+            );
+            let site = match (binding.seq_index, binding.site) {
+                (Some(seq_index), Some(site)) => Some(BindingSite::new(
+                    kmer,
+                    &kmer_ref.encodedseqs[seq_index],
+                    &record.name,
+                    &site,
+                    kmer_site,
+                )),
+                _ => None,
+            };
+
+            bindings.push(GenomeBinding {
+                kmer: kmer.clone(),
+                genome: record.name.clone(),
+                site,
+                mismatches: binding.mismatches,
+                extension_score: binding.extension_score,
+                binds: binding.binds,
+            });
+        }
+    }
+    Ok(bindings)
+}
+
+fn build_pool_graph(
+    py: Python<'_>,
+    kmers: &[Py<Kmer>],
+    t: f64,
+    params: &primaldimer::ScoringParams,
+) -> primaldimer::InteractionGraph {
+    let kmer_refs: Vec<PyRef<Kmer>> = kmers.iter().map(|k| k.as_ref(py).borrow()).collect();
+    let seqs: Vec<primaldimer::KmerSeqs> = kmer_refs.iter().map(|k| k.kmer_seqs()).collect();
+    primaldimer::InteractionGraph::from_pool(&seqs, t, params)
+}
+
+#[pyfunction(weights = "None", params = "None")]
+fn suggest_kmer_removals(
+    py: Python<'_>,
+    kmers: Vec<Py<Kmer>>,
+    t: f64,
+    weights: Option<Vec<f64>>,
+    params: Option<PyRef<ScoringParams>>,
+) -> PyResult<Vec<Py<Kmer>>> {
+    // Returns a small set of kmers whose removal leaves the pool free of dimers.
+    // weights are the cost of removing each kmer, must be finite and positive, and default to 1
+    if let Some(w) = &weights {
+        if w.len() != kmers.len() {
+            return Err(PyValueError::new_err(format!(
+                "Number of weights ({}) and kmers ({}) differ",
+                w.len(),
+                kmers.len()
+            )));
+        }
+        if let Some(bad) = w.iter().find(|x| !(x.is_finite() && **x > 0.)) {
+            return Err(PyValueError::new_err(format!(
+                "Weights must be finite and positive, not {}",
+                bad
+            )));
+        }
+    }
+    let graph = build_pool_graph(py, &kmers, t, &unwrap_params(params));
+
+    Ok(graph
+        .vertex_cover(weights.as_deref())
+        .into_iter()
+        .map(|i| kmers[i].clone())
+        .collect())
+}
+
+#[pyclass]
+pub struct KmerDegree {
+    #[pyo3(get)]
+    pub kmer: Py<Kmer>,
+    // The kmers it interacts with, and their lowest scores, worst first.
+    // Includes the kmer itself if it forms a self dimer
+    #[pyo3(get)]
+    pub partners: Vec<Py<Kmer>>,
+    #[pyo3(get)]
+    pub scores: Vec<f64>,
+}
+#[pymethods]
+impl KmerDegree {
+    #[getter]
+    pub fn degree(&self) -> usize {
+        self.partners.len()
+    }
+
+    #[getter]
+    pub fn worst_score(&self) -> Option<f64> {
+        // None if it has no partners
+        self.scores.first().copied()
+    }
+}
+
+#[pyfunction(params = "None")]
+fn kmer_interaction_degrees(
+    py: Python<'_>,
+    kmers: Vec<Py<Kmer>>,
+    t: f64,
+    params: Option<PyRef<ScoringParams>>,
+) -> Vec<KmerDegree> {
+    // For each kmer in the pool, the partners it interacts with (score <= t).
+    // The most promiscuous kmers come first, ties broken by the worst score
+    let graph = build_pool_graph(py, &kmers, t, &unwrap_params(params));
+
+    graph
+        .degrees()
+        .into_iter()
+        .map(|d| KmerDegree {
+            kmer: kmers[d.node].clone(),
+            partners: d.partners.iter().map(|(p, _)| kmers[*p].clone()).collect(),
+            scores: d.partners.iter().map(|(_, score)| *score).collect(),
+        })
+        .collect()
+}
+
+fn kmer_label(kmer: &Kmer, pool: usize, index: usize) -> String {
+    // The kmer's name, falling back to its pool and position in the pool
+    kmer.name
+        .clone()
+        .unwrap_or_else(|| format!("{}_{}", pool, index))
+}
+
+fn kmer_node_attrs(kmer: &Kmer, pool: usize, index: usize) -> primaldimer::NodeAttrs {
+    let mut attrs = vec![
+        ("label".to_string(), kmer_label(kmer, pool, index)),
+        ("pool".to_string(), pool.to_string()),
+        ("seqs".to_string(), kmer.seqs().join(",")),
+    ];
+    if let Some(start) = kmer.start {
+        attrs.push(("start".to_string(), start.to_string()));
+    }
+    if let Some(end) = kmer.end {
+        attrs.push(("end".to_string(), end.to_string()));
+    }
+    if let Some(strand) = kmer.strand() {
+        attrs.push(("strand".to_string(), strand));
+    }
+    attrs
+}
+
+#[pyfunction(kmers2 = "None", format = "\"dot\"", params = "None")]
+fn export_interaction_graph(
+    py: Python<'_>,
+    kmers1: Vec<Py<Kmer>>,
+    t: f64,
+    kmers2: Option<Vec<Py<Kmer>>>,
+    format: &str,
+    params: Option<PyRef<ScoringParams>>,
+) -> PyResult<String> {
+    // The interactions within kmers1, or between kmers1 and kmers2, as a 'dot' or 'graphml' graph.
+    // Kmers are nodes, and each interacting pair an edge with its lowest score
+    let params = unwrap_params(params);
+    let pool1: Vec<PyRef<Kmer>> = kmers1.iter().map(|k| k.as_ref(py).borrow()).collect();
+    let pool2: Vec<PyRef<Kmer>> = kmers2
+        .iter()
+        .flatten()
+        .map(|k| k.as_ref(py).borrow())
+        .collect();
+    let seqs1: Vec<primaldimer::KmerSeqs> = pool1.iter().map(|k| k.kmer_seqs()).collect();
+    let seqs2: Vec<primaldimer::KmerSeqs> = pool2.iter().map(|k| k.kmer_seqs()).collect();
+
+    let graph = match kmers2 {
+        Some(_) => primaldimer::InteractionGraph::from_pools(&seqs1, &seqs2, t, &params),
+        None => primaldimer::InteractionGraph::from_pool(&seqs1, t, &params),
+    };
+    let attrs: Vec<primaldimer::NodeAttrs> = pool1
+        .iter()
+        .enumerate()
+        .map(|(i, k)| kmer_node_attrs(k, 1, i))
+        .chain(
+            pool2
+                .iter()
+                .enumerate()
+                .map(|(i, k)| kmer_node_attrs(k, 2, i)),
+        )
+        .collect();
+
+    match format {
+        "dot" => Ok(graph.to_dot(&attrs)),
+        "graphml" => Ok(graph.to_graphml(&attrs)),
+        _ => Err(PyValueError::new_err(format!(
+            "Unknown graph format '{}', expected 'dot' or 'graphml'",
+            format
+        ))),
+    }
+}
+
+#[pyfunction(params = "None")]
+fn pool_score_matrix(
+    py: Python<'_>,
+    kmers1: Vec<Py<Kmer>>,
+    kmers2: Vec<Py<Kmer>>,
+    params: Option<PyRef<ScoringParams>>,
+) -> Vec<Vec<Option<f64>>> {
+    // The lowest score of each kmer in kmers1 (rows) with each in kmers2 (columns).
+    // None where no seqs can extend
+    let pool1: Vec<PyRef<Kmer>> = kmers1.iter().map(|k| k.as_ref(py).borrow()).collect();
+    let pool2: Vec<PyRef<Kmer>> = kmers2.iter().map(|k| k.as_ref(py).borrow()).collect();
+    let seqs1: Vec<primaldimer::KmerSeqs> = pool1.iter().map(|k| k.kmer_seqs()).collect();
+    let seqs2: Vec<primaldimer::KmerSeqs> = pool2.iter().map(|k| k.kmer_seqs()).collect();
+    primaldimer::score_matrix(&seqs1, &seqs2, &unwrap_params(params))
+}
+
+#[pyfunction(params = "None")]
+fn pool_heatmap_svg(
+    py: Python<'_>,
+    kmers1: Vec<Py<Kmer>>,
+    kmers2: Vec<Py<Kmer>>,
+    t: f64,
+    params: Option<PyRef<ScoringParams>>,
+) -> String {
+    // An SVG heatmap of pool_score_matrix, coloured either side of t, with failing cells outlined
+    let row_labels: Vec<String> = kmers1
+        .iter()
+        .enumerate()
+        .map(|(i, k)| kmer_label(&k.as_ref(py).borrow(), 1, i))
+        .collect();
+    let col_labels: Vec<String> = kmers2
+        .iter()
+        .enumerate()
+        .map(|(i, k)| kmer_label(&k.as_ref(py).borrow(), 2, i))
+        .collect();
+    let matrix = pool_score_matrix(py, kmers1, kmers2, params);
+    primaldimer::render_heatmap(&matrix, &row_labels, &col_labels, t)
+}
+
+#[pyclass]
+pub struct ShiftedKmer {
+    // A new single seq kmer at the shifted site
+    #[pyo3(get)]
+    pub kmer: Py<Kmer>,
+    #[pyo3(get)]
+    pub seq: String,
+    // The lowest score against the pool or itself. None if it never extends
+    #[pyo3(get)]
+    pub score: Option<f64>,
+    #[pyo3(get)]
+    pub tm: f64,
+    #[pyo3(get)]
+    pub tm_diff: f64,
+    #[pyo3(get)]
+    pub distance: usize,
+}
+
+#[pyfunction(max_shift = "5", params = "None")]
+fn suggest_shifted_kmers(
+    py: Python<'_>,
+    kmer: Py<Kmer>,
+    reference: &str,
+    pool: Vec<Py<Kmer>>,
+    t: f64,
+    max_shift: usize,
+    params: Option<PyRef<ScoringParams>>,
+) -> PyResult<Vec<ShiftedKmer>> {
+    // Suggests shifts of the kmer's site on the reference that form no dimers with the pool.
+    // Ranked by distance from the original site, then by Tm match. The kmer is skipped if in pool
+    let kmer_ref = kmer.as_ref(py).borrow();
+    let site = match kmer_ref.site() {
+        Some(site) => site,
+        None => return Err(PyValueError::new_err("Kmer needs start, end and strand")),
+    };
+    let reference = primaldimer::encode_reference(reference);
+    if site.start >= site.end || site.end > reference.len() {
+        return Err(PyValueError::new_err("Kmer site is outside the reference"));
+    }
+
+    let pool_refs: Vec<PyRef<Kmer>> = pool
+        .iter()
+        .filter(|k| !k.is(&kmer))
+        .map(|k| k.as_ref(py).borrow())
+        .collect();
+    let pool_seqs: Vec<primaldimer::KmerSeqs> = pool_refs.iter().map(|k| k.kmer_seqs()).collect();
+
+    primaldimer::suggest_shifted(
+        &reference,
+        site,
+        !kmer_ref.blocked_3p,
+        kmer_ref.acid(),
+        &pool_seqs,
+        max_shift,
+        t,
+        &unwrap_params(params),
+    )
+    .ok_or_else(|| PyValueError::new_err("Kmer site contains non ACGT bases"))?
+    .into_iter()
+    .map(|c| {
+        let shifted = Kmer {
+            encodedseqs: vec![c.seq.clone()],
+            start: Some(c.site.start),
+            end: Some(c.site.end),
+            strand: Some(c.site.strand),
+            name: kmer_ref.name.clone(),
+            // Cut from the reference, so it has no tail
+            tail_len: 0,
+            blocked_3p: kmer_ref.blocked_3p,
+            rna: kmer_ref.rna,
+        };
+        Ok(ShiftedKmer {
+            kmer: Py::new(py, shifted)?,
+            seq: decode_seq(&c.seq, kmer_ref.acid()),
+            score: c.score,
+            tm: c.tm,
+            tm_diff: c.tm_diff,
+            distance: c.distance,
+        })
+    })
+    .collect()
+}
+
+#[pyclass]
+pub struct LibraryHit {
+    #[pyo3(get)]
+    pub kmer: Py<Kmer>,
+    // Position of the oligo in the library
+    #[pyo3(get)]
+    pub index: usize,
+    #[pyo3(get)]
+    pub name: Option<String>,
+    #[pyo3(get)]
+    pub seq: String,
+    // The lowest score of any of the kmer's seqs with the oligo
+    #[pyo3(get)]
+    pub score: f64,
+}
+
+#[pyclass]
+pub struct OligoLibrary {
+    pub library: primaldimer::OligoLibrary,
+    pub seqs: Vec<String>,
+    pub names: Option<Vec<String>>,
+}
+#[pymethods]
+impl OligoLibrary {
+    #[new]
+    #[args(names = "None")]
+    pub fn new(seqs: Vec<String>, names: Option<Vec<String>>) -> PyResult<Self> {
+        // Provide strings in 5'-3'. The library is prepared once, and can then be
+        // screened against any number of kmers. Each screen scores the kmer against every
+        // unique oligo, bar short ones that can't interact. Seqs containing U are scored as RNA
+        if matches!(&names, Some(n) if n.len() != seqs.len()) {
+            return Err(PyValueError::new_err(
+                "names must be the same length as seqs",
+            ));
+        }
+        let encoded = seqs
+            .iter()
+            .map(|s| encode_seq(s))
+            .collect::<PyResult<Vec<_>>>()?;
+        Ok(OligoLibrary {
+            library: primaldimer::OligoLibrary::new(&encoded),
+            seqs,
+            names,
+        })
+    }
+
+    pub fn __len__(&self) -> usize {
+        self.library.len()
+    }
+
+    #[getter]
+    pub fn n_unique(&self) -> usize {
+        // Identical oligos are only scored once
+        self.library.n_unique()
+    }
+
+    #[args(params = "None")]
+    pub fn screen(
+        &self,
+        py: Python<'_>,
+        kmers: Vec<Py<Kmer>>,
+        t: f64,
+        params: Option<PyRef<ScoringParams>>,
+    ) -> PyResult<Vec<LibraryHit>> {
+        // Every kmer / library oligo pair that interacts (score <= t), by kmer then oligo
+        let params = unwrap_params(params);
+        let mut hits = Vec::new();
+        for kmer in kmers.iter() {
+            py.check_signals()?;
+            // The lowest score of each oligo with any of the kmer's seqs
+            let scores = self
+                .library
+                .screen(&kmer.as_ref(py).borrow().kmer_seqs(), t, &params);
+
+            hits.extend(scores.into_iter().map(|(index, score)| LibraryHit {
+                kmer: kmer.clone(),
+                index,
+                name: self.names.as_ref().map(|n| n[index].clone()),
+                seq: self.seqs[index].clone(),
+                score,
+            }));
+        }
+        Ok(hits)
+    }
+
+    pub fn __repr__(&self) -> String {
+        format!(
+            "OligoLibrary(size={}, unique={})",
+            self.library.len(),
+            self.library.n_unique()
+        )
+    }
 }
 
 /// A Python module implemented in Rust.
@@ -120,8 +1882,48 @@ fn primaldimer_py(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(do_pools_interact_py, m)?)?;
     m.add_function(wrap_pyfunction!(do_seqs_interact_py, m)?)?;
     m.add_function(wrap_pyfunction!(calc_at_offset_py, m)?)?;
+    m.add_function(wrap_pyfunction!(calc_at_offset_breakdown_py, m)?)?;
     m.add_function(wrap_pyfunction!(which_kmers_pools_interact, m)?)?;
+    m.add_function(wrap_pyfunction!(which_kmers_pools_interact_prob, m)?)?;
+    m.add_function(wrap_pyfunction!(iter_interacting_kmers, m)?)?;
+    m.add_function(wrap_pyfunction!(which_kmers_pools_interact_tailed, m)?)?;
+    m.add_function(wrap_pyfunction!(do_pools_interact_prob_py, m)?)?;
+    m.add_function(wrap_pyfunction!(calc_seqs_score_py, m)?)?;
+    m.add_function(wrap_pyfunction!(calc_tm_py, m)?)?;
+    m.add_function(wrap_pyfunction!(score_to_probability_py, m)?)?;
+    m.add_function(wrap_pyfunction!(probability_to_score_py, m)?)?;
+    m.add_function(wrap_pyfunction!(fit_scoring_params_py, m)?)?;
+    m.add_function(wrap_pyfunction!(evaluate_thresholds_py, m)?)?;
+    m.add_function(wrap_pyfunction!(predict_dimer_product_py, m)?)?;
+    m.add_function(wrap_pyfunction!(which_kmers_short_products, m)?)?;
+    m.add_function(wrap_pyfunction!(check_pool, m)?)?;
+    m.add_function(wrap_pyfunction!(scan_kmers_reference, m)?)?;
+    m.add_function(wrap_pyfunction!(in_silico_pcr, m)?)?;
+    m.add_function(wrap_pyfunction!(kmers_genome_binding, m)?)?;
+    m.add_function(wrap_pyfunction!(suggest_kmer_removals, m)?)?;
+    m.add_function(wrap_pyfunction!(suggest_shifted_kmers, m)?)?;
+    m.add_function(wrap_pyfunction!(export_interaction_graph, m)?)?;
+    m.add_function(wrap_pyfunction!(pool_score_matrix, m)?)?;
+    m.add_function(wrap_pyfunction!(pool_heatmap_svg, m)?)?;
+    m.add_function(wrap_pyfunction!(kmer_interaction_degrees, m)?)?;
     m.add_class::<Kmer>()?;
+    m.add_class::<ScoreBreakdown>()?;
+    m.add_class::<FittedParams>()?;
+    m.add_class::<ScoringParams>()?;
+    m.add_class::<ThresholdEvaluation>()?;
+    m.add_class::<BackgroundModel>()?;
+    m.add_class::<Significance>()?;
+    m.add_class::<DimerProduct>()?;
+    m.add_class::<PoolCheck>()?;
+    m.add_class::<BindingSite>()?;
+    m.add_class::<Amplicon>()?;
+    m.add_class::<GenomeBinding>()?;
+    m.add_class::<ShiftedKmer>()?;
+    m.add_class::<KmerDegree>()?;
+    m.add_class::<PairCache>()?;
+    m.add_class::<InteractingPairs>()?;
+    m.add_class::<OligoLibrary>()?;
+    m.add_class::<LibraryHit>()?;
     Ok(())
 }
 
@@ -129,6 +1931,16 @@ fn primaldimer_py(_py: Python, m: &PyModule) -> PyResult<()> {
 mod tests {
     use super::*;
 
+    fn kmers_min_score(
+        kmer1: &Kmer,
+        kmer2: &Kmer,
+        params: &primaldimer::ScoringParams,
+    ) -> Option<f64> {
+        // The lowest score between any of their seqs, in the directions that can extend
+        let policy = primaldimer::TailPolicy::default();
+        primaldimer::calc_kmers_min_score(&kmer1.kmer_seqs(), &kmer2.kmer_seqs(), &policy, params)
+    }
+
     #[test]
     fn test_kmer_new() {
         // Test creating a new Kmer instance with valid sequences
@@ -154,12 +1966,20 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "Sequence contains not ACGT bases: ATCGX")]
+    #[should_panic(expected = "Sequence contains not ACGTU bases: ATCGX")]
     fn test_kmer_new_invalid_seq() {
         // Test creating a new Kmer instance with an invalid sequence
         Kmer::new(0, vec!["ATCG".to_string(), "ATCGX".to_string()]);
     }
 
+    #[test]
+    fn test_kmer_mixed_acid() {
+        // RNA kmers are scored with RNA stacks, so can't also contain T
+        let kmer = Kmer::new(0, vec!["ACGU".to_string(), "GCUA".to_string()]);
+        assert_eq!(kmer.kmer_seqs().acid, primaldimer::NucleicAcid::Rna);
+        assert!(Kmer::try_new(0, vec!["ACGU".to_string(), "ACGT".to_string()]).is_err());
+    }
+
     #[test]
     fn test_kmer_seqs() {
         // Test getting the sequences in ATCG format
@@ -167,6 +1987,87 @@ mod tests {
         assert_eq!(kmer.seqs(), vec!["ATCG", "GCTA"]);
     }
 
+    #[test]
+    fn test_kmer_site() {
+        // A site needs all coordinates and the strand
+        let mut kmer = Kmer::new(0, vec!["ATCG".to_string()]);
+        kmer.start = Some(10);
+        kmer.end = Some(14);
+        assert_eq!(kmer.site(), None);
+
+        kmer.strand = Some(primaldimer::Strand::Forward);
+        assert_eq!(
+            kmer.site(),
+            Some(primaldimer::Site {
+                start: 10,
+                end: 14,
+                strand: primaldimer::Strand::Forward
+            })
+        );
+    }
+
+    #[test]
+    fn test_kmer_label() {
+        // The graph and heatmap share labels
+        let mut kmer = Kmer::new(0, vec!["ATCG".to_string()]);
+        assert_eq!(kmer_label(&kmer, 2, 3), "2_3");
+        kmer.name = Some("amplicon_1_LEFT".to_string());
+        assert_eq!(kmer_label(&kmer, 2, 3), "amplicon_1_LEFT");
+        assert_eq!(kmer_node_attrs(&kmer, 2, 3)[0].1, "amplicon_1_LEFT");
+    }
+
+    #[test]
+    fn test_kmer_blocked_3p() {
+        // A 3' blocked kmer is scored as one that can't extend
+        let mut kmer = Kmer::new(0, vec!["ACACCTGTGCCTGTTAAACCAT".to_string()]);
+        assert!(kmer.kmer_seqs().extendable);
+        kmer.blocked_3p = true;
+        assert!(!kmer.kmer_seqs().extendable);
+    }
+
+    #[test]
+    fn test_kmers_min_score_once() {
+        // The pruned and cached scores used by InteractingPairs agree with kmers_min_score
+        let kmer1 = Kmer::new(0, vec!["ACACCTGTGCCTGTTAAACCAT".to_string()]);
+        let kmer2 = Kmer::new(
+            1,
+            vec![
+                "TGGAAATACCCACAAGTTAATGGTTTAAC".to_string(),
+                "ACTCCCATGGCATAGCTCCAAA".to_string(),
+            ],
+        );
+        let params = primaldimer::DEFAULT_PARAMS;
+        let mut cache = primaldimer::PairCache::new(10);
+        let score = kmers_min_score(&kmer1, &kmer2, &params);
+        assert!(score.unwrap() <= -26.);
+        assert_eq!(kmers_min_score_below(&kmer1, &kmer2, -26., &params), score);
+        assert_eq!(kmers_min_score_below(&kmer1, &kmer2, -60., &params), None);
+        let policy = primaldimer::TailPolicy::default();
+        let (k1, k2) = (&kmer1.kmer_seqs(), &kmer2.kmer_seqs());
+        assert_eq!(
+            kmers_min_score_cached(k1, k2, &policy, &params, &mut cache),
+            score
+        );
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_kmer_rna() {
+        let kmer = Kmer::new(0, vec!["ACGU".to_string()]);
+        assert!(kmer.rna);
+        assert_eq!(kmer.seqs(), vec!["ACGU"]);
+        assert_eq!(
+            kmer.encodedseqs,
+            Kmer::new(0, vec!["ACGT".to_string()]).encodedseqs
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Sequences mix T and U")]
+    fn test_kmer_mixed_rna() {
+        Kmer::new(0, vec!["ACGT".to_string(), "ACGU".to_string()]);
+    }
+
     #[test]
     fn test_kmer_lens() {
         // Test getting the lengths of the sequences
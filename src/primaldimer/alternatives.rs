@@ -0,0 +1,247 @@
+use super::reference::{reverse_complement, OTHER_BASE};
+use super::tm::{calc_tm, calc_tm_hybrid};
+use super::{calc_kmers_min_score, KmerSeqs, NucleicAcid, ScoringParams, Site, Strand, TailPolicy};
+
+/// A shifted version of a primer site that avoids dimers with its pool
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShiftedCandidate {
+    pub site: Site,
+    // 5'-3' on the site's strand
+    pub seq: Vec<usize>,
+    // The lowest score against the pool or itself. None if it never extends
+    pub score: Option<f64>,
+    pub tm: f64,
+    // Absolute difference from the original primer's Tm
+    pub tm_diff: f64,
+    // Bases moved by the start plus bases moved by the end
+    pub distance: usize,
+}
+
+fn site_seq(reference: &[usize], site: &Site) -> Option<Vec<usize>> {
+    // The primer seq a site encodes, or None if it is off the reference or contains non ACGT bases
+    let window = reference.get(site.start..site.end)?;
+    if window.contains(&OTHER_BASE) {
+        return None;
+    }
+    match site.strand {
+        Strand::Forward => Some(window.to_vec()),
+        Strand::Reverse => Some(reverse_complement(window)),
+    }
+}
+
+fn primer_tm(seq: &[usize], acid: NucleicAcid) -> f64 {
+    // The Tm of the primer on the DNA reference. An RNA primer forms a hybrid with the
+    // reference strand it binds, its reverse complement
+    match acid {
+        NucleicAcid::Dna => calc_tm(seq),
+        NucleicAcid::Rna => calc_tm_hybrid(&reverse_complement(seq)),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn suggest_shifted(
+    reference: &[usize],
+    original: Site,
+    extendable: bool,
+    acid: NucleicAcid,
+    pool: &[KmerSeqs],
+    max_shift: usize,
+    t: f64,
+    params: &ScoringParams,
+) -> Option<Vec<ShiftedCandidate>> {
+    // Moves the start and end of the original site by up to max_shift bases each, and keeps the
+    // candidates that form no dimer (score <= t) with any kmer in the pool or with themselves.
+    // Ranked by distance from the original, then by how closely the Tm matches.
+    // extendable is false if the original is 3' blocked, as are its candidates.
+    // The candidates are scored, and their Tm found, as the original's acid.
+    // None if the original site is off the reference or contains non ACGT bases
+    let original_tm = primer_tm(&site_seq(reference, &original)?, acid);
+
+    let policy = TailPolicy::default();
+    let mut candidates: Vec<ShiftedCandidate> = Vec::new();
+    let start_lo = original.start.saturating_sub(max_shift);
+    let end_hi = (original.end + max_shift).min(reference.len());
+
+    for start in start_lo..=original.start + max_shift {
+        for end in original.end.saturating_sub(max_shift)..=end_hi {
+            // Primers need at least two bases to be scored
+            if end < start + 2 || (start == original.start && end == original.end) {
+                continue;
+            }
+            let site = Site {
+                start,
+                end,
+                strand: original.strand,
+            };
+            let seq = match site_seq(reference, &site) {
+                Some(seq) => seq,
+                None => continue,
+            };
+
+            // Self dimer, then each kmer in the pool
+            let seqs = [seq];
+            let candidate = KmerSeqs {
+                seqs: &seqs,
+                extendable,
+                acid,
+                // Cut from the reference, so it has no tail
+                tail_len: 0,
+            };
+            let mut score = calc_kmers_min_score(&candidate, &candidate, &policy, params);
+            for kmer in pool.iter() {
+                if let Some(s) = calc_kmers_min_score(&candidate, kmer, &policy, params) {
+                    score = Some(score.map_or(s, |best| best.min(s)));
+                }
+            }
+            if matches!(score, Some(s) if s <= t) {
+                continue;
+            }
+
+            let [seq] = seqs;
+            let tm = primer_tm(&seq, acid);
+            candidates.push(ShiftedCandidate {
+                site,
+                seq,
+                score,
+                tm,
+                tm_diff: (tm - original_tm).abs(),
+                distance: start.abs_diff(original.start) + end.abs_diff(original.end),
+            });
+        }
+    }
+
+    candidates.sort_by(|a, b| {
+        a.distance
+            .cmp(&b.distance)
+            .then(a.tm_diff.total_cmp(&b.tm_diff))
+    });
+    Some(candidates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{encode_base, DEFAULT_PARAMS};
+    use super::*;
+    const DNA: NucleicAcid = NucleicAcid::Dna;
+    fn build_reference() -> (Vec<usize>, Site) {
+        // nCoV-2019_76_RIGHT_0 at 20..42
+        let mut reference = encode_base("TTGACCGTAGGCTAGCATTG");
+        reference.extend(encode_base("ACACCTGTGCCTGTTAAACCAT"));
+        reference.extend(encode_base("GATCGTTAGCAAGTCAGTCA"));
+        let site = Site {
+            start: 20,
+            end: 42,
+            strand: Strand::Forward,
+        };
+        (reference, site)
+    }
+    #[test]
+    fn test_ranked_by_distance() {
+        let (reference, site) = build_reference();
+        let candidates =
+            suggest_shifted(&reference, site, true, DNA, &[], 2, -1000., &DEFAULT_PARAMS).unwrap();
+        // Every combination of start and end, bar the original
+        assert_eq!(candidates.len(), 24);
+        assert!(candidates.iter().all(|c| c.distance > 0));
+        assert!(candidates
+            .windows(2)
+            .all(|w| (w[0].distance, w[0].tm_diff) <= (w[1].distance, w[1].tm_diff)));
+        assert_eq!(
+            candidates[0].seq,
+            reference[candidates[0].site.start..candidates[0].site.end]
+        );
+    }
+    #[test]
+    fn test_reverse_strand() {
+        let (reference, mut site) = build_reference();
+        site.strand = Strand::Reverse;
+        let candidates =
+            suggest_shifted(&reference, site, true, DNA, &[], 1, -1000., &DEFAULT_PARAMS).unwrap();
+        for c in candidates.iter() {
+            assert_eq!(
+                c.seq,
+                reverse_complement(&reference[c.site.start..c.site.end])
+            );
+        }
+    }
+    #[test]
+    fn test_excludes_dimers() {
+        // The complement of the region around the original binds every shifted candidate
+        let (reference, site) = build_reference();
+        let clash = vec![reverse_complement(&reference[site.start - 3..site.end + 3])];
+        let pool = [KmerSeqs::new(&clash)];
+        assert!(
+            suggest_shifted(&reference, site, true, DNA, &pool, 2, -26., &DEFAULT_PARAMS)
+                .unwrap()
+                .is_empty()
+        );
+    }
+    #[test]
+    fn test_blocked() {
+        // extendable is passed through to the scoring of every candidate
+        let (reference, site) = build_reference();
+        let clash = vec![reverse_complement(&reference[site.start - 3..site.end + 3])];
+        let pool = [KmerSeqs::new(&clash)];
+        let policy = TailPolicy::default();
+        for extendable in [true, false] {
+            let candidates = suggest_shifted(
+                &reference,
+                site,
+                extendable,
+                DNA,
+                &pool,
+                1,
+                -1000.,
+                &DEFAULT_PARAMS,
+            )
+            .unwrap();
+            for c in candidates.iter() {
+                let seqs = [c.seq.clone()];
+                let mut candidate = KmerSeqs::new(&seqs);
+                candidate.extendable = extendable;
+                let expected = [&candidate, &pool[0]]
+                    .into_iter()
+                    .filter_map(|kmer| {
+                        calc_kmers_min_score(&candidate, kmer, &policy, &DEFAULT_PARAMS)
+                    })
+                    .min_by(|a, b| a.total_cmp(b));
+                assert_eq!(c.score, expected);
+            }
+        }
+    }
+    #[test]
+    fn test_invalid_original() {
+        let (mut reference, site) = build_reference();
+        let off_end = Site {
+            start: 50,
+            end: 70,
+            strand: Strand::Forward,
+        };
+        let shifted = |reference: &[usize], site| {
+            suggest_shifted(reference, site, true, DNA, &[], 1, -1000., &DEFAULT_PARAMS)
+        };
+        assert_eq!(shifted(&reference, off_end), None);
+        reference[site.start + 3] = OTHER_BASE;
+        assert_eq!(shifted(&reference, site), None);
+    }
+    #[test]
+    fn test_rna_tm() {
+        // An RNA primer's candidates are ranked by their hybrid Tm with the reference
+        let (reference, site) = build_reference();
+        let candidates = suggest_shifted(
+            &reference,
+            site,
+            true,
+            NucleicAcid::Rna,
+            &[],
+            1,
+            -1000.,
+            &DEFAULT_PARAMS,
+        )
+        .unwrap();
+        for c in candidates.iter() {
+            assert_eq!(c.tm, calc_tm_hybrid(&reverse_complement(&c.seq)));
+            assert!(c.tm < calc_tm(&c.seq));
+        }
+    }
+}
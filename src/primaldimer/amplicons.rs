@@ -0,0 +1,211 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::reference::{scan_reference_acids, BindingSite};
+use super::{NucleicAcid, ScoringParams};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Strand {
+    Forward,
+    Reverse,
+}
+
+impl FromStr for Strand {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "+" => Ok(Strand::Forward),
+            "-" => Ok(Strand::Reverse),
+            _ => Err(format!("Strand must be '+' or '-': {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Strand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Strand::Forward => write!(f, "+"),
+            Strand::Reverse => write!(f, "-"),
+        }
+    }
+}
+
+/// Where an oligo binds on the reference. 0-based, half open
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Site {
+    pub start: usize,
+    pub end: usize,
+    pub strand: Strand,
+}
+
+pub fn calc_product_len(forward: &Site, reverse: &Site) -> Option<usize> {
+    // A product runs from the 5' end of the forward to the 5' end of the reverse.
+    // None if the reverse isn't downstream of the forward
+    if reverse.end > forward.start && reverse.start >= forward.start && reverse.end >= forward.end {
+        Some(reverse.end - forward.start)
+    } else {
+        None
+    }
+}
+
+pub fn find_products(sites: &[Site], max_len: usize) -> Vec<(usize, usize, usize)> {
+    // Returns (forward index, reverse index, product length) for every
+    // forward / reverse pair that would amplify a product of at most max_len
+    let mut products = Vec::new();
+    for (f_index, forward) in sites.iter().enumerate() {
+        if forward.strand != Strand::Forward {
+            continue;
+        }
+        for (r_index, reverse) in sites.iter().enumerate() {
+            if reverse.strand != Strand::Reverse {
+                continue;
+            }
+            if let Some(len) = calc_product_len(forward, reverse) {
+                if len <= max_len {
+                    products.push((f_index, r_index, len));
+                }
+            }
+        }
+    }
+    products
+}
+
+pub fn find_short_products(sites: &[Site], max_len: usize) -> Vec<(usize, usize, usize)> {
+    // As find_products, but only products shorter than max_len
+    let mut products = find_products(sites, max_len);
+    products.retain(|(_f, _r, len)| *len < max_len);
+    products
+}
+
+/// A product amplified by two primers binding the reference
+#[derive(Debug, Clone, PartialEq)]
+pub struct PcrProduct {
+    // The index of each primer, and where it binds
+    pub forward: (usize, BindingSite),
+    pub reverse: (usize, BindingSite),
+    pub length: usize,
+}
+
+pub fn in_silico_pcr(
+    primers: &[(&[usize], NucleicAcid)],
+    reference: &[usize],
+    t: f64,
+    max_len: usize,
+    params: &ScoringParams,
+) -> Vec<PcrProduct> {
+    // Every product of at most max_len from any primer binding the forward strand
+    // with a score <= t, and any downstream primer binding the reverse.
+    // Primers are (seq, acid). PCR amplifies DNA, so the reference is DNA
+    let sites: Vec<(usize, BindingSite)> = primers
+        .iter()
+        .enumerate()
+        .flat_map(|(index, (primer, acid))| {
+            scan_reference_acids(primer, reference, (*acid, NucleicAcid::Dna), t, params)
+                .into_iter()
+                .map(move |site| (index, site))
+        })
+        .collect();
+    let plain_sites: Vec<Site> = sites.iter().map(|(_index, s)| s.site).collect();
+
+    find_products(&plain_sites, max_len)
+        .into_iter()
+        .map(|(f, r, length)| PcrProduct {
+            forward: sites[f].clone(),
+            reverse: sites[r].clone(),
+            length,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::reference::reverse_complement;
+    use super::super::DEFAULT_PARAMS;
+    use super::*;
+
+    fn site(start: usize, end: usize, strand: Strand) -> Site {
+        Site { start, end, strand }
+    }
+
+    #[test]
+    fn test_strand_round_trip() {
+        for s in ["+", "-"] {
+            assert_eq!(s.parse::<Strand>().unwrap().to_string(), s);
+        }
+        assert!("x".parse::<Strand>().is_err());
+    }
+    #[test]
+    fn test_overlapping_amplicons() {
+        // amplicon_1: 100-400, amplicon_2: 350-650, in the same pool
+        let sites = [
+            site(100, 125, Strand::Forward),
+            site(375, 400, Strand::Reverse),
+            site(350, 375, Strand::Forward),
+            site(625, 650, Strand::Reverse),
+        ];
+        // amplicon_2 LEFT and amplicon_1 RIGHT make a 50bp product
+        assert_eq!(find_short_products(&sites, 100), vec![(2, 1, 50)]);
+        // The intended products are 300bp
+        assert_eq!(find_short_products(&sites, 301).len(), 3);
+    }
+    #[test]
+    fn test_find_products_inclusive() {
+        let sites = [
+            site(100, 125, Strand::Forward),
+            site(375, 400, Strand::Reverse),
+        ];
+        assert_eq!(find_products(&sites, 300), vec![(0, 1, 300)]);
+        assert!(find_short_products(&sites, 300).is_empty());
+    }
+    #[test]
+    fn test_no_product_upstream_reverse() {
+        // A reverse upstream of the forward can't make a product
+        let forward = site(500, 520, Strand::Forward);
+        let reverse = site(100, 120, Strand::Reverse);
+        assert_eq!(calc_product_len(&forward, &reverse), None);
+    }
+    #[test]
+    fn test_in_silico_pcr() {
+        // A random reference, with the primers' sites copied to make an off-target product
+        let mut state: u64 = 7;
+        let mut reference: Vec<usize> = (0..800)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (state >> 62) as usize
+            })
+            .collect();
+        let forward = reference[50..75].to_vec();
+        let reverse = reverse_complement(&reference[275..300]);
+        reference.splice(500..525, forward.iter().copied());
+        reference.splice(700..725, reference[275..300].to_vec());
+        let primers = [
+            (&forward[..], NucleicAcid::Dna),
+            (&reverse[..], NucleicAcid::Dna),
+        ];
+
+        let products = in_silico_pcr(&primers, &reference, -26., 400, &DEFAULT_PARAMS);
+        let summary: Vec<(usize, usize, usize)> = products
+            .iter()
+            .map(|p| (p.forward.1.site.start, p.reverse.1.site.end, p.length))
+            .collect();
+        // The intended product, and the off-target copy. 50-725 is over max_len
+        assert_eq!(summary, vec![(50, 300, 250), (500, 725, 225)]);
+        assert!(products
+            .iter()
+            .all(|p| p.forward.0 == 0 && p.reverse.0 == 1));
+
+        // max_len is inclusive
+        let products = in_silico_pcr(&primers, &reference, -26., 225, &DEFAULT_PARAMS);
+        assert_eq!(products.len(), 1);
+        assert_eq!(products[0].length, 225);
+
+        // An RNA primer binds the same sites, scored as a hybrid
+        let rna_primers = [(&forward[..], NucleicAcid::Rna), primers[1]];
+        let rna_products = in_silico_pcr(&rna_primers, &reference, -26., 225, &DEFAULT_PARAMS);
+        assert_eq!(rna_products[0].forward.1.site, products[0].forward.1.site);
+        assert_ne!(rna_products[0].forward.1.score, products[0].forward.1.score);
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+
+use super::{calc_seqs_min_score_acids, NucleicAcid, ScoringParams};
+
+// (len, gc_count, acid) of each sequence in a pair
+type BackgroundKey = ((usize, usize, NucleicAcid), (usize, usize, NucleicAcid));
+
+/// SplitMix64. Small, seedable and good enough for shuffling bases
+struct Rng {
+    state: u64,
+}
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+fn random_seq(len: usize, gc_count: usize, rng: &mut Rng) -> Vec<usize> {
+    // A random sequence with exactly gc_count G/C bases
+    // base_to_encode = {"A": 0, "T": 3, "C": 1, "G": 2}
+    let mut seq: Vec<usize> = (0..len)
+        .map(|i| match i < gc_count {
+            true => 1 + rng.below(2),
+            false => 3 * rng.below(2),
+        })
+        .collect();
+    // Fisher-Yates shuffle
+    for i in (1..len).rev() {
+        seq.swap(i, rng.below(i + 1));
+    }
+    seq
+}
+
+fn gc_count(seq: &[usize]) -> usize {
+    seq.iter().filter(|b| **b == 1 || **b == 2).count()
+}
+
+/// The significance of an observed score against the background
+#[derive(Debug, Clone, PartialEq)]
+pub struct Significance {
+    pub score: Option<f64>,
+    // Fraction of background pairs scoring <= score, with a pseudocount
+    pub p_value: f64,
+    // None if there is no score, or the background has no spread
+    pub z_score: Option<f64>,
+}
+
+/// Scores length and GC matched random pairs, caching the distribution for each
+pub struct BackgroundModel {
+    pub n_samples: usize,
+    pub seed: u64,
+    pub params: ScoringParams,
+    cache: HashMap<BackgroundKey, Vec<Option<f64>>>,
+}
+
+impl BackgroundModel {
+    pub fn new(n_samples: usize, seed: u64, params: ScoringParams) -> BackgroundModel {
+        BackgroundModel {
+            n_samples,
+            seed,
+            params,
+            cache: HashMap::new(),
+        }
+    }
+
+    pub fn distribution(
+        &mut self,
+        seq1: (usize, usize),
+        seq2: (usize, usize),
+        acids: (NucleicAcid, NucleicAcid),
+    ) -> &Vec<Option<f64>> {
+        // The background scores for (len, gc_count) pairs, scored as acids.
+        // Scores are symmetric so the key is ordered
+        for (len, gc) in [seq1, seq2] {
+            if gc > len {
+                panic!("GC count ({}) is greater than the length ({})", gc, len);
+            }
+        }
+        let (seq1, seq2) = ((seq1.0, seq1.1, acids.0), (seq2.0, seq2.1, acids.1));
+        let key = match seq1 <= seq2 {
+            true => (seq1, seq2),
+            false => (seq2, seq1),
+        };
+
+        let n_samples = self.n_samples;
+        let params = self.params;
+        // Seed each key separately so the result doesn't depend on call order
+        let seed = self.seed
+            ^ (key.0 .0 as u64) << 48
+            ^ (key.0 .1 as u64) << 32
+            ^ (key.1 .0 as u64) << 16
+            ^ (key.1 .1 as u64);
+
+        self.cache.entry(key).or_insert_with(|| {
+            let mut rng = Rng::new(seed);
+            (0..n_samples)
+                .map(|_| {
+                    let s1 = random_seq(key.0 .0, key.0 .1, &mut rng);
+                    let s2 = random_seq(key.1 .0, key.1 .1, &mut rng);
+                    let acids = (key.0 .2, key.1 .2);
+                    calc_seqs_min_score_acids(&s1, &s2, acids, (true, true), &params)
+                })
+                .collect()
+        })
+    }
+
+    pub fn significance(
+        &mut self,
+        seq1: &[usize],
+        seq2: &[usize],
+        acids: (NucleicAcid, NucleicAcid),
+    ) -> Significance {
+        // The background pairs are matched to seq1 and seq2 in length, GC count and acid
+        let score = calc_seqs_min_score_acids(seq1, seq2, acids, (true, true), &self.params);
+        let (key1, key2) = ((seq1.len(), gc_count(seq1)), (seq2.len(), gc_count(seq2)));
+        let background = self.distribution(key1, key2, acids);
+
+        // Background pairs that can't extend never score <= the observed score
+        let as_low = match score {
+            Some(s) => background
+                .iter()
+                .filter(|b| matches!(b, Some(b) if *b <= s))
+                .count(),
+            None => background.len(),
+        };
+        let p_value = (as_low + 1) as f64 / (background.len() + 1) as f64;
+
+        let scores: Vec<f64> = background.iter().flatten().copied().collect();
+        let z_score = match (score, scores.len() > 1) {
+            (Some(s), true) => {
+                let mean = scores.iter().sum::<f64>() / scores.len() as f64;
+                let var = scores.iter().map(|x| (x - mean).powi(2)).sum::<f64>()
+                    / (scores.len() - 1) as f64;
+                match var > 0. {
+                    true => Some((s - mean) / var.sqrt()),
+                    false => None,
+                }
+            }
+            _ => None,
+        };
+
+        Significance {
+            score,
+            p_value,
+            z_score,
+        }
+    }
+
+    pub fn cache_size(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.cache.clear()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{encode_base, DEFAULT_PARAMS};
+    use super::*;
+
+    const DNA_PAIR: (NucleicAcid, NucleicAcid) = (NucleicAcid::Dna, NucleicAcid::Dna);
+    #[test]
+    fn test_random_seq_gc() {
+        let mut rng = Rng::new(1);
+        let seq = random_seq(30, 12, &mut rng);
+        assert_eq!(seq.len(), 30);
+        assert_eq!(gc_count(&seq), 12);
+    }
+    #[test]
+    fn test_seeded_distribution() {
+        let mut model1 = BackgroundModel::new(50, 42, DEFAULT_PARAMS);
+        let mut model2 = BackgroundModel::new(50, 42, DEFAULT_PARAMS);
+
+        // Call order and pair order shouldn't change the distribution
+        model2.distribution((25, 10), (22, 11), DNA_PAIR);
+        assert_eq!(
+            model1.distribution((22, 11), (25, 10), DNA_PAIR).clone(),
+            model2.distribution((25, 10), (22, 11), DNA_PAIR).clone()
+        );
+        assert_eq!(model2.cache_size(), 1);
+
+        // An RNA seq is a separate background
+        let rna = (NucleicAcid::Rna, NucleicAcid::Dna);
+        model2.distribution((25, 10), (22, 11), rna);
+        assert_eq!(model2.cache_size(), 2);
+        model2.clear_cache();
+        assert_eq!(model2.cache_size(), 0);
+    }
+    #[test]
+    #[should_panic]
+    fn test_gc_above_len() {
+        let mut model = BackgroundModel::new(10, 0, DEFAULT_PARAMS);
+        model.distribution((20, 21), (20, 10), DNA_PAIR);
+    }
+    #[test]
+    fn test_significance() {
+        // nCoV-2019_76_RIGHT_0 nCoV-2019_18_LEFT_0
+        let s1 = encode_base("ACACCTGTGCCTGTTAAACCAT");
+        let s2 = encode_base("TGGAAATACCCACAAGTTAATGGTTTAAC");
+        let mut model = BackgroundModel::new(200, 0, DEFAULT_PARAMS);
+
+        // A strong dimer should be rare in the background
+        let sig = model.significance(&s1, &s2, DNA_PAIR);
+        assert!(sig.p_value < 0.05);
+        assert!(sig.z_score.unwrap() < 0.);
+    }
+}
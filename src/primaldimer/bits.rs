@@ -0,0 +1,136 @@
+// Bit parallel matching. Each base is split across two bit planes, so with
+// base_to_encode = {"A": 0, "T": 3, "C": 1, "G": 2} a base matches its
+// complement when both planes differ (b ^ 3)
+
+// The longest seq that can be packed
+pub const MAX_PACKED_LEN: usize = 128;
+
+/// A seq packed into two bit planes. Bit x holds base x
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PackedSeq {
+    lo: u128,
+    hi: u128,
+    pub len: usize,
+}
+
+impl PackedSeq {
+    pub fn new(seq: &[usize]) -> Option<PackedSeq> {
+        // None if the seq is too long to pack
+        if seq.len() > MAX_PACKED_LEN {
+            return None;
+        }
+        let mut packed = PackedSeq {
+            lo: 0,
+            hi: 0,
+            len: seq.len(),
+        };
+        for (x, base) in seq.iter().enumerate() {
+            packed.lo |= ((base & 1) as u128) << x;
+            packed.hi |= ((base >> 1) as u128) << x;
+        }
+        Some(packed)
+    }
+}
+
+pub fn range_mask(first: usize, end: usize) -> u128 {
+    // Bits first..end set
+    let below = |n: usize| match n {
+        MAX_PACKED_LEN => u128::MAX,
+        n => (1 << n) - 1,
+    };
+    below(end) & !below(first)
+}
+
+pub fn match_mask(seq1: &PackedSeq, seq2: &PackedSeq, offset: i32) -> u128 {
+    // Bit x is set if seq1[x] matches seq2[x + offset], for every x where both exist
+    let shift = |plane: u128| match offset >= 0 {
+        true => plane >> offset,
+        false => plane << -offset,
+    };
+    let first = (-(offset as i64)).max(0) as usize;
+    let end = (seq2.len as i64 - offset as i64).clamp(0, seq1.len as i64) as usize;
+    if first >= end {
+        return 0;
+    }
+    (seq1.lo ^ shift(seq2.lo)) & (seq1.hi ^ shift(seq2.hi)) & range_mask(first, end)
+}
+
+/// The runs of matches and mismatches in a match mask, as group_match_bool
+#[derive(Debug, Clone)]
+pub struct MatchRuns {
+    mask: u128,
+    pos: usize,
+    end: usize,
+}
+
+impl MatchRuns {
+    pub fn new(mask: u128, first: usize, end: usize) -> MatchRuns {
+        MatchRuns {
+            mask,
+            pos: first,
+            end,
+        }
+    }
+}
+
+impl Iterator for MatchRuns {
+    type Item = (bool, usize);
+
+    fn next(&mut self) -> Option<(bool, usize)> {
+        if self.pos >= self.end {
+            return None;
+        }
+        let rest = self.mask >> self.pos;
+        let is_match = rest & 1 == 1;
+        // Bits past end are clear, so a run of mismatches is cut short at end
+        let count = match is_match {
+            true => rest.trailing_ones() as usize,
+            false => (rest.trailing_zeros() as usize).min(self.end - self.pos),
+        };
+        self.pos += count;
+        Some((is_match, count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{encode_base, group_match_bool, MATCH_ARRAY};
+    use super::*;
+    #[test]
+    fn test_match_mask() {
+        let s1 = encode_base("ACGTAC");
+        let s2 = encode_base("TGCAAGTT");
+        for offset in -4..3 {
+            let mask = match_mask(
+                &PackedSeq::new(&s1).unwrap(),
+                &PackedSeq::new(&s2).unwrap(),
+                offset,
+            );
+            for (x, base) in s1.iter().enumerate() {
+                let expected = match s2.get((x as i32 + offset) as usize) {
+                    Some(b) if x as i32 + offset >= 0 => MATCH_ARRAY[*base][*b],
+                    _ => false,
+                };
+                assert_eq!(mask >> x & 1 == 1, expected);
+            }
+        }
+    }
+    #[test]
+    fn test_match_runs() {
+        let match_bool = [false, true, true, false, false, false, true, false];
+        let mask = match_bool
+            .iter()
+            .enumerate()
+            .fold(0, |m, (x, b)| m | (*b as u128) << (x + 2));
+        let runs: Vec<(bool, usize)> = MatchRuns::new(mask, 2, 10).collect();
+        assert_eq!(runs, group_match_bool(&match_bool));
+    }
+    #[test]
+    fn test_max_len() {
+        let seq = vec![3; MAX_PACKED_LEN];
+        let packed = PackedSeq::new(&seq).unwrap();
+        let comp = PackedSeq::new(&vec![0; MAX_PACKED_LEN]).unwrap();
+        assert_eq!(match_mask(&packed, &comp, 0), u128::MAX);
+        assert!(PackedSeq::new(&vec![0; MAX_PACKED_LEN + 1]).is_none());
+    }
+}
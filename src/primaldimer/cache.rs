@@ -0,0 +1,183 @@
+use std::collections::{BTreeMap, HashMap};
+
+use super::{calc_kmer_seqs_min_score, KmerSeqs, NucleicAcid, ScoringParams, TailPolicy};
+
+// A seq, with its kmer's acid, if it can extend, and tail length
+type SeqKey = (Vec<usize>, NucleicAcid, bool, usize);
+// (seq, seq, policy as bits, params as bits). The seqs are ordered, as the min score is symmetric
+type CacheKey = (SeqKey, SeqKey, [u64; 2], [u64; 10]);
+
+/// Memoises calc_kmers_min_score per seq pair, evicting the least recently used pair once full
+#[derive(Debug, Clone)]
+pub struct PairCache {
+    pub capacity: usize,
+    pub hits: u64,
+    pub misses: u64,
+    // key -> (min score, tick of last use)
+    entries: HashMap<CacheKey, (Option<f64>, u64)>,
+    // tick of last use -> key, oldest first
+    order: BTreeMap<u64, CacheKey>,
+    tick: u64,
+}
+
+impl PairCache {
+    pub fn new(capacity: usize) -> PairCache {
+        PairCache {
+            capacity,
+            hits: 0,
+            misses: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        // Drops every entry and resets the statistics
+        self.entries.clear();
+        self.order.clear();
+        self.hits = 0;
+        self.misses = 0;
+    }
+
+    pub fn min_score(
+        &mut self,
+        kmer1: &KmerSeqs,
+        seq1: &[usize],
+        kmer2: &KmerSeqs,
+        seq2: &[usize],
+        policy: &TailPolicy,
+        params: &ScoringParams,
+    ) -> Option<f64> {
+        // The min score of seq1 from kmer1 and seq2 from kmer2, as in calc_kmers_min_score.
+        // From the cache if the pair has been scored with this policy and these params
+        let seq_key = |kmer: &KmerSeqs, seq: &[usize]| -> SeqKey {
+            (seq.to_vec(), kmer.acid, kmer.extendable, kmer.tail_len)
+        };
+        let (a, b) = (seq_key(kmer1, seq1), seq_key(kmer2, seq2));
+        let (a, b) = match a <= b {
+            true => (a, b),
+            false => (b, a),
+        };
+        let policy_bits = [policy.tail_tail.to_bits(), policy.tail_primer.to_bits()];
+        let key: CacheKey = (a, b, policy_bits, params.bonus.map(f64::to_bits));
+        self.tick += 1;
+
+        if let Some((score, last_used)) = self.entries.get_mut(&key) {
+            self.hits += 1;
+            let key = self.order.remove(last_used).unwrap();
+            *last_used = self.tick;
+            let score = *score;
+            self.order.insert(self.tick, key);
+            return score;
+        }
+
+        self.misses += 1;
+        let score = calc_kmer_seqs_min_score(kmer1, seq1, kmer2, seq2, policy, params);
+        if self.capacity == 0 {
+            return score;
+        }
+        if self.entries.len() >= self.capacity {
+            if let Some((_tick, oldest)) = self.order.pop_first() {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.entries.insert(key.clone(), (score, self.tick));
+        self.order.insert(self.tick, key);
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{calc_seqs_min_score_acids, encode_base, DEFAULT_PARAMS};
+    use super::*;
+    fn dna_min_score(
+        cache: &mut PairCache,
+        seq1: &[usize],
+        seq2: &[usize],
+        params: &ScoringParams,
+    ) -> Option<f64> {
+        let kmer = KmerSeqs::new(&[]);
+        cache.min_score(&kmer, seq1, &kmer, seq2, &TailPolicy::default(), params)
+    }
+    #[test]
+    fn test_symmetric_hits() {
+        let s1 = encode_base("ACACCTGTGCCTGTTAAACCAT");
+        let s2 = encode_base("TGGAAATACCCACAAGTTAATGGTTTAAC");
+        let mut cache = PairCache::new(10);
+
+        let score = dna_min_score(&mut cache, &s1, &s2, &DEFAULT_PARAMS);
+        assert_eq!(
+            score,
+            calc_seqs_min_score_acids(
+                &s1,
+                &s2,
+                (NucleicAcid::Dna, NucleicAcid::Dna),
+                (true, true),
+                &DEFAULT_PARAMS
+            )
+        );
+        assert_eq!(dna_min_score(&mut cache, &s2, &s1, &DEFAULT_PARAMS), score);
+        assert_eq!((cache.hits, cache.misses, cache.len()), (1, 1, 1));
+
+        // Different params are a different entry
+        let mut params = DEFAULT_PARAMS;
+        params.bonus[0] += 1.;
+        dna_min_score(&mut cache, &s1, &s2, &params);
+        assert_eq!((cache.hits, cache.misses, cache.len()), (1, 2, 2));
+
+        cache.clear();
+        assert!(cache.is_empty());
+        assert_eq!((cache.hits, cache.misses), (0, 0));
+    }
+    #[test]
+    fn test_lru_eviction() {
+        let seqs: Vec<Vec<usize>> = ["ACGTAA", "CCGTAT", "GGATCC"]
+            .iter()
+            .map(|s| encode_base(s))
+            .collect();
+        let mut cache = PairCache::new(2);
+        dna_min_score(&mut cache, &seqs[0], &seqs[0], &DEFAULT_PARAMS);
+        dna_min_score(&mut cache, &seqs[1], &seqs[1], &DEFAULT_PARAMS);
+        // Use 0 again, so 1 is the least recently used
+        dna_min_score(&mut cache, &seqs[0], &seqs[0], &DEFAULT_PARAMS);
+        dna_min_score(&mut cache, &seqs[2], &seqs[2], &DEFAULT_PARAMS);
+        assert_eq!(cache.len(), 2);
+
+        dna_min_score(&mut cache, &seqs[0], &seqs[0], &DEFAULT_PARAMS);
+        assert_eq!(cache.hits, 2);
+        dna_min_score(&mut cache, &seqs[1], &seqs[1], &DEFAULT_PARAMS);
+        assert_eq!(cache.misses, 4);
+    }
+    #[test]
+    fn test_kmer_keys() {
+        // A blocked or tailed kmer is a different entry, and either order of the pair hits
+        let s1 = encode_base("ACACCTGTGCCTGTTAAACCAT");
+        let s2 = encode_base("TGGAAATACCCACAAGTTAATGGTTTAAC");
+        let (dna, mut blocked) = (KmerSeqs::new(&[]), KmerSeqs::new(&[]));
+        blocked.extendable = false;
+        let policy = TailPolicy::default();
+        let mut cache = PairCache::new(10);
+
+        let score = cache.min_score(&blocked, &s1, &dna, &s2, &policy, &DEFAULT_PARAMS);
+        assert_eq!(
+            cache.min_score(&dna, &s2, &blocked, &s1, &policy, &DEFAULT_PARAMS),
+            score
+        );
+        assert_eq!((cache.hits, cache.misses), (1, 1));
+        dna_min_score(&mut cache, &s1, &s2, &DEFAULT_PARAMS);
+        blocked.extendable = true;
+        blocked.tail_len = 5;
+        cache.min_score(&blocked, &s1, &dna, &s2, &policy, &DEFAULT_PARAMS);
+        assert_eq!((cache.hits, cache.misses, cache.len()), (1, 3, 3));
+    }
+}
@@ -0,0 +1,148 @@
+use super::{calc_seqs_min_score_acids, EncodedPair, ScoringParams};
+
+/// ROC and PR curves for the decision rule `score <= threshold`.
+/// Each index of the curve vecs is one threshold, from strictest to most permissive
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThresholdEvaluation {
+    // The lowest score of each pair. None if neither seq can extend
+    pub scores: Vec<Option<f64>>,
+    pub thresholds: Vec<f64>,
+    pub fpr: Vec<f64>,
+    pub tpr: Vec<f64>,
+    pub precision: Vec<f64>,
+    pub roc_auc: f64,
+    pub pr_auc: f64,
+}
+
+impl ThresholdEvaluation {
+    pub fn threshold_for_fpr(&self, target_fpr: f64) -> Option<f64> {
+        // The most permissive threshold that keeps the false positive rate <= target_fpr
+        self.thresholds
+            .iter()
+            .zip(self.fpr.iter())
+            .rev()
+            .find(|(threshold, fpr)| **fpr <= target_fpr && threshold.is_finite())
+            .map(|(threshold, _fpr)| *threshold)
+    }
+}
+
+pub fn evaluate_thresholds(
+    pairs: &[EncodedPair],
+    labels: &[bool],
+    params: &ScoringParams,
+) -> ThresholdEvaluation {
+    let scores: Vec<Option<f64>> = pairs
+        .iter()
+        .map(|(seq1, seq2, acids)| {
+            calc_seqs_min_score_acids(seq1, seq2, *acids, (true, true), params)
+        })
+        .collect();
+    evaluate_scores(scores, labels)
+}
+
+pub fn evaluate_scores(scores: Vec<Option<f64>>, labels: &[bool]) -> ThresholdEvaluation {
+    if scores.len() != labels.len() {
+        panic!(
+            "Number of scores ({}) and labels ({}) differ",
+            scores.len(),
+            labels.len()
+        );
+    }
+    let n_pos = labels.iter().filter(|l| **l).count();
+    let n_neg = labels.len() - n_pos;
+    if n_pos == 0 || n_neg == 0 {
+        panic!("Both positive and negative labels are required");
+    }
+
+    // Pairs that can't extend are never called, so sort them last
+    let mut ranked: Vec<(f64, bool)> = scores
+        .iter()
+        .zip(labels.iter())
+        .map(|(score, label)| (score.unwrap_or(f64::INFINITY), *label))
+        .collect();
+    ranked.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    // Start with nothing called
+    let mut thresholds = vec![f64::NEG_INFINITY];
+    let mut fpr = vec![0.];
+    let mut tpr = vec![0.];
+    let mut precision = vec![1.];
+
+    let mut tp = 0;
+    let mut fp = 0;
+    for (index, (score, label)) in ranked.iter().enumerate() {
+        match label {
+            true => tp += 1,
+            false => fp += 1,
+        }
+        // Only emit a point once all pairs with this score are included
+        if ranked.get(index + 1).map(|(s, _)| s) == Some(score) {
+            continue;
+        }
+        thresholds.push(*score);
+        fpr.push(fp as f64 / n_neg as f64);
+        tpr.push(tp as f64 / n_pos as f64);
+        precision.push(tp as f64 / (tp + fp) as f64);
+    }
+
+    // Trapezoid ROC AUC, and average precision for PR AUC
+    let mut roc_auc = 0.;
+    let mut pr_auc = 0.;
+    for i in 1..thresholds.len() {
+        roc_auc += (fpr[i] - fpr[i - 1]) * (tpr[i] + tpr[i - 1]) / 2.;
+        pr_auc += (tpr[i] - tpr[i - 1]) * precision[i];
+    }
+
+    ThresholdEvaluation {
+        scores,
+        thresholds,
+        fpr,
+        tpr,
+        precision,
+        roc_auc,
+        pr_auc,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_perfect_separation() {
+        let scores = vec![Some(-40.), Some(-35.), Some(-10.), None];
+        let labels = [true, true, false, false];
+        let eval = evaluate_scores(scores, &labels);
+
+        assert_eq!(eval.roc_auc, 1.);
+        assert_eq!(eval.pr_auc, 1.);
+        assert_eq!(eval.threshold_for_fpr(0.), Some(-35.));
+        assert_eq!(eval.threshold_for_fpr(0.5), Some(-10.));
+    }
+    #[test]
+    fn test_inverted_separation() {
+        let scores = vec![Some(-10.), Some(-40.)];
+        let labels = [true, false];
+        let eval = evaluate_scores(scores, &labels);
+
+        assert_eq!(eval.roc_auc, 0.);
+        // Every threshold that calls the positive also calls the negative
+        assert_eq!(eval.threshold_for_fpr(0.), None);
+    }
+    #[test]
+    fn test_tied_scores() {
+        // Ties should produce a single point
+        let scores = vec![Some(-30.), Some(-30.), Some(-20.)];
+        let labels = [true, false, false];
+        let eval = evaluate_scores(scores, &labels);
+
+        assert_eq!(eval.thresholds, vec![f64::NEG_INFINITY, -30., -20.]);
+        assert_eq!(eval.tpr, vec![0., 1., 1.]);
+        assert_eq!(eval.fpr, vec![0., 0.5, 1.]);
+        assert_eq!(eval.roc_auc, 0.75);
+    }
+    #[test]
+    #[should_panic]
+    fn test_single_class() {
+        evaluate_scores(vec![Some(-30.)], &[true]);
+    }
+}
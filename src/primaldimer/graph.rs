@@ -0,0 +1,327 @@
+use super::{calc_kmers_min_score, KmerSeqs, ScoringParams, TailPolicy};
+
+// (name, value) attributes of a node, written to exported graphs
+pub type NodeAttrs = Vec<(String, String)>;
+
+fn escape_dot(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+pub fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// The interactions of a single node
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeDegree {
+    pub node: usize,
+    // (partner, lowest score), lowest score first. Includes the node itself for a self dimer
+    pub partners: Vec<(usize, f64)>,
+}
+
+/// Kmers as nodes, with an edge between each pair that interacts
+#[derive(Debug, Clone, PartialEq)]
+pub struct InteractionGraph {
+    pub n_nodes: usize,
+    // (node, node, lowest score). A node can have an edge to itself
+    pub edges: Vec<(usize, usize, f64)>,
+}
+
+impl InteractionGraph {
+    pub fn from_pool(kmers: &[KmerSeqs], t: f64, params: &ScoringParams) -> InteractionGraph {
+        // Each unordered pair, including each kmer with itself, is checked once.
+        // Tails are weighted by the default TailPolicy
+        let policy = TailPolicy::default();
+        let mut edges = Vec::new();
+        for (i, kmer1) in kmers.iter().enumerate() {
+            for (j, kmer2) in kmers.iter().enumerate().skip(i) {
+                if let Some(score) = calc_kmers_min_score(kmer1, kmer2, &policy, params) {
+                    if score <= t {
+                        edges.push((i, j, score));
+                    }
+                }
+            }
+        }
+        InteractionGraph {
+            n_nodes: kmers.len(),
+            edges,
+        }
+    }
+
+    pub fn from_pools(
+        pool1: &[KmerSeqs],
+        pool2: &[KmerSeqs],
+        t: f64,
+        params: &ScoringParams,
+    ) -> InteractionGraph {
+        // Only pairs across the pools are checked. Nodes are pool1 then pool2
+        let policy = TailPolicy::default();
+        let mut edges = Vec::new();
+        for (i, kmer1) in pool1.iter().enumerate() {
+            for (j, kmer2) in pool2.iter().enumerate() {
+                if let Some(score) = calc_kmers_min_score(kmer1, kmer2, &policy, params) {
+                    if score <= t {
+                        edges.push((i, pool1.len() + j, score));
+                    }
+                }
+            }
+        }
+        InteractionGraph {
+            n_nodes: pool1.len() + pool2.len(),
+            edges,
+        }
+    }
+
+    pub fn to_dot(&self, attrs: &[NodeAttrs]) -> String {
+        // Graphviz format. Edges carry their lowest score as the score attribute
+        let mut dot = String::from("graph interactions {\n");
+        for node in 0..self.n_nodes {
+            let node_attrs: Vec<String> = attrs
+                .get(node)
+                .into_iter()
+                .flatten()
+                .map(|(name, value)| format!("{}=\"{}\"", escape_dot(name), escape_dot(value)))
+                .collect();
+            dot.push_str(&format!("  n{} [{}];\n", node, node_attrs.join(", ")));
+        }
+        for (a, b, score) in self.edges.iter() {
+            dot.push_str(&format!("  n{} -- n{} [score={}];\n", a, b, score));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn to_graphml(&self, attrs: &[NodeAttrs]) -> String {
+        // GraphML, as read by Cytoscape. Node attributes are strings, the edge score a double
+        let mut keys: Vec<&str> = Vec::new();
+        for (name, _value) in attrs.iter().flatten() {
+            if !keys.contains(&name.as_str()) {
+                keys.push(name);
+            }
+        }
+
+        let mut xml = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n"
+        ));
+        for (k, name) in keys.iter().enumerate() {
+            xml.push_str(&format!(
+                "  <key id=\"d{}\" for=\"node\" attr.name=\"{}\" attr.type=\"string\"/>\n",
+                k,
+                escape_xml(name)
+            ));
+        }
+        xml.push_str(
+            "  <key id=\"score\" for=\"edge\" attr.name=\"score\" attr.type=\"double\"/>\n",
+        );
+        xml.push_str("  <graph id=\"interactions\" edgedefault=\"undirected\">\n");
+        for node in 0..self.n_nodes {
+            xml.push_str(&format!("    <node id=\"n{}\">\n", node));
+            for (name, value) in attrs.get(node).into_iter().flatten() {
+                let k = keys.iter().position(|key| key == name).unwrap();
+                xml.push_str(&format!(
+                    "      <data key=\"d{}\">{}</data>\n",
+                    k,
+                    escape_xml(value)
+                ));
+            }
+            xml.push_str("    </node>\n");
+        }
+        for (a, b, score) in self.edges.iter() {
+            xml.push_str(&format!(
+                "    <edge source=\"n{}\" target=\"n{}\">\n      <data key=\"score\">{}</data>\n    </edge>\n",
+                a, b, score
+            ));
+        }
+        xml.push_str("  </graph>\n</graphml>\n");
+        xml
+    }
+
+    pub fn degrees(&self) -> Vec<NodeDegree> {
+        // The partners of every node, most partners first, ties broken by the worst score
+        let mut degrees: Vec<NodeDegree> = (0..self.n_nodes)
+            .map(|node| NodeDegree {
+                node,
+                partners: Vec::new(),
+            })
+            .collect();
+        for (a, b, score) in self.edges.iter() {
+            degrees[*a].partners.push((*b, *score));
+            if a != b {
+                degrees[*b].partners.push((*a, *score));
+            }
+        }
+        for degree in degrees.iter_mut() {
+            degree.partners.sort_by(|x, y| x.1.total_cmp(&y.1));
+        }
+
+        let worst = |d: &NodeDegree| d.partners.first().map_or(f64::INFINITY, |p| p.1);
+        degrees.sort_by(|x, y| {
+            y.partners
+                .len()
+                .cmp(&x.partners.len())
+                .then(worst(x).total_cmp(&worst(y)))
+        });
+        degrees
+    }
+
+    pub fn vertex_cover(&self, weights: Option<&[f64]>) -> Vec<usize> {
+        // Approximate minimum weight vertex cover. Removing these nodes leaves no edges.
+        // Uses the local ratio method (within 2x of optimal), then drops any node
+        // whose edges are all covered by other nodes. Weights must be finite and positive
+        let mut residual: Vec<f64> = match weights {
+            Some(w) => w.to_vec(),
+            None => vec![1.; self.n_nodes],
+        };
+        let mut in_cover = vec![false; self.n_nodes];
+
+        for (a, b, _score) in self.edges.iter() {
+            if in_cover[*a] || in_cover[*b] {
+                continue;
+            }
+            let delta = residual[*a].min(residual[*b]);
+            residual[*a] -= delta;
+            residual[*b] -= delta;
+            for node in [*a, *b] {
+                if residual[node] <= 0. {
+                    in_cover[node] = true;
+                }
+            }
+        }
+
+        // Remove redundant nodes, most costly first
+        let mut cover: Vec<usize> = (0..self.n_nodes).filter(|n| in_cover[*n]).collect();
+        if let Some(w) = weights {
+            cover.sort_by(|a, b| w[*b].total_cmp(&w[*a]));
+        }
+        for node in cover.iter() {
+            let redundant = self.edges.iter().all(|(a, b, _score)| {
+                (*a != *node && *b != *node)
+                    || (*a != *b && in_cover[if *a == *node { *b } else { *a }])
+            });
+            if redundant {
+                in_cover[*node] = false;
+            }
+        }
+
+        (0..self.n_nodes).filter(|n| in_cover[*n]).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{encode_base, DEFAULT_PARAMS};
+    use super::*;
+
+    fn graph(n_nodes: usize, edges: &[(usize, usize)]) -> InteractionGraph {
+        InteractionGraph {
+            n_nodes,
+            edges: edges.iter().map(|(a, b)| (*a, *b, -30.)).collect(),
+        }
+    }
+
+    fn is_cover(graph: &InteractionGraph, cover: &[usize]) -> bool {
+        graph
+            .edges
+            .iter()
+            .all(|(a, b, _)| cover.contains(a) || cover.contains(b))
+    }
+
+    #[test]
+    fn test_star() {
+        // A hub interacting with everything should be the only removal
+        let g = graph(5, &[(0, 1), (0, 2), (0, 3), (0, 4)]);
+        assert_eq!(g.vertex_cover(None), vec![0]);
+    }
+    #[test]
+    fn test_self_dimer() {
+        // A self dimer must be removed
+        let g = graph(3, &[(1, 1), (0, 2)]);
+        let cover = g.vertex_cover(None);
+        assert!(cover.contains(&1));
+        assert!(is_cover(&g, &cover));
+        assert_eq!(cover.len(), 2);
+    }
+    #[test]
+    fn test_weighted() {
+        // The hub is costly to redesign, so remove the leaves instead
+        let g = graph(3, &[(0, 1), (0, 2)]);
+        assert_eq!(g.vertex_cover(Some(&[10., 1., 1.])), vec![1, 2]);
+    }
+    #[test]
+    fn test_no_edges() {
+        assert!(graph(3, &[]).vertex_cover(None).is_empty());
+    }
+    #[test]
+    fn test_degrees() {
+        let mut g = graph(4, &[(0, 1), (1, 1), (1, 2), (2, 3)]);
+        g.edges[3].2 = -50.;
+        let degrees = g.degrees();
+        let order: Vec<(usize, usize)> =
+            degrees.iter().map(|d| (d.node, d.partners.len())).collect();
+        // 0 and 3 have one partner each, but 3 has the worse score
+        assert_eq!(order, vec![(1, 3), (2, 2), (3, 1), (0, 1)]);
+        assert_eq!(degrees[1].partners, vec![(3, -50.), (1, -30.)]);
+    }
+    #[test]
+    fn test_to_dot() {
+        let g = graph(2, &[(0, 1)]);
+        let attrs = vec![
+            vec![("label".to_string(), "a\"b".to_string())],
+            vec![("label".to_string(), "c".to_string())],
+        ];
+        assert_eq!(
+            g.to_dot(&attrs),
+            "graph interactions {\n  n0 [label=\"a\\\"b\"];\n  n1 [label=\"c\"];\n  n0 -- n1 [score=-30];\n}\n"
+        );
+    }
+    #[test]
+    fn test_to_graphml() {
+        let g = graph(2, &[(0, 1)]);
+        let attrs = vec![
+            vec![("label".to_string(), "a<b".to_string())],
+            vec![
+                ("label".to_string(), "c".to_string()),
+                ("strand".to_string(), "+".to_string()),
+            ],
+        ];
+        let xml = g.to_graphml(&attrs);
+        assert!(xml.contains("<key id=\"d1\" for=\"node\" attr.name=\"strand\""));
+        assert!(xml.contains("<data key=\"d0\">a&lt;b</data>"));
+        assert!(xml.contains("<data key=\"d1\">+</data>"));
+        assert!(xml.contains("<edge source=\"n0\" target=\"n1\">"));
+    }
+    #[test]
+    fn test_from_pool_extendable() {
+        // Each kmer's extendable is passed through to calc_kmers_min_score
+        let seqs = [
+            vec![encode_base("ACACCTGTGCCTGTTAAACCAT")],
+            vec![encode_base("TGGAAATACCCACAAGTTAATGGTTTAAC")],
+        ];
+        let mut kmers = [KmerSeqs::new(&seqs[0]), KmerSeqs::new(&seqs[1])];
+        let pair_scores = |g: InteractionGraph| -> Vec<f64> {
+            g.edges
+                .iter()
+                .filter(|(a, b, _)| a != b)
+                .map(|(_, _, score)| *score)
+                .collect()
+        };
+        for extendable in [(true, false), (false, true), (false, false)] {
+            (kmers[0].extendable, kmers[1].extendable) = extendable;
+            let policy = TailPolicy::default();
+            let expected: Vec<f64> =
+                calc_kmers_min_score(&kmers[0], &kmers[1], &policy, &DEFAULT_PARAMS)
+                    .into_iter()
+                    .collect();
+            let pool = InteractionGraph::from_pool(&kmers, 0., &DEFAULT_PARAMS);
+            let pools = InteractionGraph::from_pools(&kmers[..1], &kmers[1..], 0., &DEFAULT_PARAMS);
+            assert_eq!(pair_scores(pool), expected);
+            assert_eq!(pair_scores(pools), expected);
+        }
+    }
+}
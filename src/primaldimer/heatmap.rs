@@ -0,0 +1,207 @@
+use super::graph::escape_xml;
+use super::{calc_kmers_min_score, KmerSeqs, ScoringParams, TailPolicy};
+
+// Pixel sizes of the rendered heatmap
+const CELL_SIZE: usize = 16;
+const CHAR_WIDTH: usize = 7;
+const LEGEND_HEIGHT: usize = 40;
+const LEGEND_CHARS: usize = 40;
+
+// Colours of the diverging scale. Scores below the threshold are red, above are blue
+const FAIL_COLOUR: (f64, f64, f64) = (178., 24., 43.);
+const PASS_COLOUR: (f64, f64, f64) = (33., 102., 172.);
+const NO_SCORE_COLOUR: &str = "#e0e0e0";
+
+pub fn score_matrix(
+    pool1: &[KmerSeqs],
+    pool2: &[KmerSeqs],
+    params: &ScoringParams,
+) -> Vec<Vec<Option<f64>>> {
+    // The lowest score of each kmer in pool1 (rows) with each kmer in pool2 (columns).
+    // None where no seqs can extend. Tails are weighted by the default TailPolicy
+    let policy = TailPolicy::default();
+    pool1
+        .iter()
+        .map(|kmer1| {
+            pool2
+                .iter()
+                .map(|kmer2| calc_kmers_min_score(kmer1, kmer2, &policy, params))
+                .collect()
+        })
+        .collect()
+}
+
+fn score_colour(score: f64, t: f64, span: f64) -> String {
+    // White at t, fading to the fail or pass colour at t -/+ span
+    let frac = ((score - t) / span).clamp(-1., 1.);
+    let (r, g, b) = if frac < 0. { FAIL_COLOUR } else { PASS_COLOUR };
+    let mix = |c: f64| (255. - (255. - c) * frac.abs()).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", mix(r), mix(g), mix(b))
+}
+
+pub fn render_heatmap(
+    matrix: &[Vec<Option<f64>>],
+    row_labels: &[String],
+    col_labels: &[String],
+    t: f64,
+) -> String {
+    // A self contained SVG of the matrix. Cells scoring <= t are outlined
+    let n_rows = matrix.len();
+    let n_cols = col_labels.len();
+
+    // The colour scale spans the largest distance from t
+    let span = matrix
+        .iter()
+        .flatten()
+        .flatten()
+        .map(|score| (score - t).abs())
+        .fold(1., f64::max);
+
+    let label_len = |labels: &[String]| labels.iter().map(|l| l.len()).max().unwrap_or(0);
+    let left = label_len(row_labels) * CHAR_WIDTH + 10;
+    let top = label_len(col_labels) * CHAR_WIDTH + 10;
+    // Wide enough for the legend, which is at most LEGEND_CHARS long
+    let width = left + (n_cols * CELL_SIZE).max(LEGEND_CHARS * CHAR_WIDTH) + 10;
+    let height = top + n_rows * CELL_SIZE + LEGEND_HEIGHT;
+
+    let mut svg = format!(
+        concat!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" ",
+            "font-family=\"monospace\" font-size=\"11\">\n",
+            "  <rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n"
+        ),
+        width, height
+    );
+
+    // Axis labels
+    for (i, label) in row_labels.iter().enumerate() {
+        svg.push_str(&format!(
+            "  <text x=\"{}\" y=\"{}\" text-anchor=\"end\" dominant-baseline=\"middle\">{}</text>\n",
+            left - 4,
+            top + i * CELL_SIZE + CELL_SIZE / 2,
+            escape_xml(label)
+        ));
+    }
+    for (j, label) in col_labels.iter().enumerate() {
+        let x = left + j * CELL_SIZE + CELL_SIZE / 2;
+        svg.push_str(&format!(
+            "  <text x=\"{}\" y=\"{}\" transform=\"rotate(-90 {} {})\" dominant-baseline=\"middle\">{}</text>\n",
+            x,
+            top - 4,
+            x,
+            top - 4,
+            escape_xml(label)
+        ));
+    }
+
+    // Cells, then the outlines of failing cells so they are drawn on top
+    let mut outlines = String::new();
+    for (i, row) in matrix.iter().enumerate() {
+        for (j, score) in row.iter().enumerate() {
+            let (x, y) = (left + j * CELL_SIZE, top + i * CELL_SIZE);
+            let (fill, text) = match score {
+                Some(s) => (score_colour(*s, t, span), format!("{:.2}", s)),
+                None => (NO_SCORE_COLOUR.to_string(), "none".to_string()),
+            };
+            svg.push_str(&format!(
+                "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"><title>{} / {}: {}</title></rect>\n",
+                x,
+                y,
+                CELL_SIZE,
+                CELL_SIZE,
+                fill,
+                escape_xml(&row_labels[i]),
+                escape_xml(&col_labels[j]),
+                text
+            ));
+            if matches!(score, Some(s) if *s <= t) {
+                outlines.push_str(&format!(
+                    "  <rect class=\"fail\" x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"black\" stroke-width=\"2\"/>\n",
+                    x, y, CELL_SIZE, CELL_SIZE
+                ));
+            }
+        }
+    }
+    svg.push_str(&outlines);
+
+    // Legend of the colour scale, from t - span to t + span
+    let legend_y = top + n_rows * CELL_SIZE + 10;
+    svg.push_str(&format!(
+        concat!(
+            "  <text x=\"{}\" y=\"{}\" dominant-baseline=\"middle\">",
+            "<tspan fill=\"{}\">{:.1}</tspan> &lt; threshold {:.1} &lt; <tspan fill=\"{}\">{:.1}</tspan>",
+            "</text>\n"
+        ),
+        left,
+        legend_y + CELL_SIZE / 2,
+        score_colour(t - span, t, span),
+        t - span,
+        t,
+        score_colour(t + span, t, span),
+        t + span
+    ));
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{encode_base, DEFAULT_PARAMS};
+    use super::*;
+    #[test]
+    fn test_score_colour() {
+        assert_eq!(score_colour(-26., -26., 10.), "#ffffff");
+        assert_eq!(score_colour(-100., -26., 10.), "#b2182b");
+        assert_eq!(score_colour(0., -26., 10.), "#2166ac");
+    }
+    #[test]
+    fn test_score_matrix() {
+        let k1 = vec![encode_base("ACACCTGTGCCTGTTAAACCAT")];
+        let k2 = vec![encode_base("GGCACAGGTGTAAA")];
+        let pool1 = [KmerSeqs::new(&k1), KmerSeqs::new(&k2)];
+        let pool2 = [KmerSeqs::new(&k2)];
+        let matrix = score_matrix(&pool1, &pool2, &DEFAULT_PARAMS);
+        assert_eq!(matrix.len(), 2);
+        assert_eq!(matrix[0].len(), 1);
+        assert_eq!(
+            matrix[0][0],
+            calc_kmers_min_score(
+                &pool1[0],
+                &pool2[0],
+                &TailPolicy::default(),
+                &DEFAULT_PARAMS
+            )
+        );
+    }
+    #[test]
+    fn test_score_matrix_extendable() {
+        // Each kmer's extendable is passed through to calc_kmers_min_score
+        let k1 = vec![encode_base("ACACCTGTGCCTGTTAAACCAT")];
+        let k2 = vec![encode_base("TGGAAATACCCACAAGTTAATGGTTTAAC")];
+        let mut pool1 = [KmerSeqs::new(&k1)];
+        let mut pool2 = [KmerSeqs::new(&k2)];
+        for extendable in [(true, false), (false, true), (false, false)] {
+            (pool1[0].extendable, pool2[0].extendable) = extendable;
+            assert_eq!(
+                score_matrix(&pool1, &pool2, &DEFAULT_PARAMS)[0][0],
+                calc_kmers_min_score(
+                    &pool1[0],
+                    &pool2[0],
+                    &TailPolicy::default(),
+                    &DEFAULT_PARAMS
+                )
+            );
+        }
+    }
+    #[test]
+    fn test_render_heatmap() {
+        let matrix = vec![vec![Some(-40.), None], vec![Some(-10.), Some(-26.)]];
+        let rows = vec!["a<1".to_string(), "b".to_string()];
+        let cols = vec!["c".to_string(), "d".to_string()];
+        let svg = render_heatmap(&matrix, &rows, &cols, -26.);
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("a&lt;1 / c: -40.00"));
+        // Only the cells <= t are outlined
+        assert_eq!(svg.matches("class=\"fail\"").count(), 2);
+    }
+}
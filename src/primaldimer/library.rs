@@ -0,0 +1,504 @@
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+use super::bits::PackedSeq;
+use super::rna::nn_table;
+use super::scores::{NnTable, MATCH_ARRAY};
+use super::{
+    calc_match_prop_bonus, calc_seqs_min_score_tailed, seq1_min_score_below, BoundTerms, KmerSeqs,
+    NucleicAcid, ScoringParams, TailPolicy,
+};
+
+// The length of the seeds in the index
+const SEED_LEN: usize = 4;
+
+fn seed_key(window: &[usize], complement: bool) -> Option<usize> {
+    // Two bits per base. None if any base can't pair
+    window
+        .iter()
+        .try_fold(0, |key, base| match (*base < 4, complement) {
+            (true, true) => Some(key * 4 + 3 - base),
+            (true, false) => Some(key * 4 + base),
+            (false, _) => None,
+        })
+}
+
+/// A unique oligo in the library, packed in both orientations
+#[derive(Debug, Clone)]
+struct LibraryOligo {
+    seq: Vec<usize>,
+    seq_rev: Vec<usize>,
+    acid: NucleicAcid,
+    // None if too long to pack
+    packed: Option<(PackedSeq, PackedSeq)>,
+    // The index of each copy in the input
+    members: Vec<usize>,
+}
+
+/// A large set of oligos, such as barcodes or adapters, screened in a batch against
+/// each primer. Identical oligos are scored once, and every oligo is packed once.
+/// Short oligos sharing no seed with a primer are skipped, but every other oligo is
+/// scored, so a screen still grows with the size of the library
+#[derive(Debug, Clone)]
+pub struct OligoLibrary {
+    oligos: Vec<LibraryOligo>,
+    // The unique oligos holding each SEED_LEN-mer of their reversed seq, by seed_key.
+    // Only used to skip oligos shorter than a primer's SeedlessReach
+    seeds: Vec<Vec<usize>>,
+    n_input: usize,
+}
+
+impl OligoLibrary {
+    pub fn new(seqs: &[(Vec<usize>, NucleicAcid)]) -> OligoLibrary {
+        // Identical oligos are only scored once
+        let mut oligos: Vec<LibraryOligo> = Vec::new();
+        let mut unique: HashMap<(&[usize], NucleicAcid), usize> = HashMap::new();
+        for (index, (seq, acid)) in seqs.iter().enumerate() {
+            match unique.get(&(seq.as_slice(), *acid)) {
+                Some(u) => oligos[*u].members.push(index),
+                None => {
+                    unique.insert((seq, *acid), oligos.len());
+                    let seq_rev: Vec<usize> = seq.iter().rev().copied().collect();
+                    let packed = PackedSeq::new(seq).zip(PackedSeq::new(&seq_rev));
+                    oligos.push(LibraryOligo {
+                        seq: seq.clone(),
+                        seq_rev,
+                        acid: *acid,
+                        packed,
+                        members: vec![index],
+                    });
+                }
+            }
+        }
+
+        let mut seeds: Vec<Vec<usize>> = vec![Vec::new(); 1 << (2 * SEED_LEN)];
+        for (u, oligo) in oligos.iter().enumerate() {
+            for window in oligo.seq_rev.windows(SEED_LEN) {
+                if let Some(key) = seed_key(window, false) {
+                    if seeds[key].last() != Some(&u) {
+                        seeds[key].push(u);
+                    }
+                }
+            }
+        }
+        OligoLibrary {
+            oligos,
+            seeds,
+            n_input: seqs.len(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.n_input
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n_input == 0
+    }
+
+    pub fn n_unique(&self) -> usize {
+        self.oligos.len()
+    }
+
+    pub fn screen(&self, kmer: &KmerSeqs, t: f64, params: &ScoringParams) -> Vec<(usize, f64)> {
+        // Every library oligo that any of the kmer's seqs interacts with (score <= t), as
+        // (index in the library, lowest score), in library order.
+        // The scores are those of calc_kmers_min_score. Library oligos can always extend,
+        // and have no tail. The kmer's tail is weighted by the default TailPolicy
+        let policy = TailPolicy::default();
+        let tails = (kmer.tail_len, 0);
+        let mut scores: Vec<Option<f64>> = vec![None; self.oligos.len()];
+        let max_len = self.oligos.iter().map(|o| o.seq.len()).max().unwrap_or(0);
+
+        for seq in kmer.seqs.iter() {
+            let seq_rev: Vec<usize> = seq.iter().rev().copied().collect();
+            let packed_seq = PackedSeq::new(seq).zip(PackedSeq::new(&seq_rev));
+
+            // A run of matches between seq and an oligo is a SEED_LEN-mer of the oligo's
+            // reversed seq that complements one of seq, in either direction
+            let mut seeded = vec![false; self.oligos.len()];
+            for window in seq.windows(SEED_LEN) {
+                if let Some(key) = seed_key(window, true) {
+                    for u in self.seeds[key].iter() {
+                        seeded[*u] = true;
+                    }
+                }
+            }
+            // By oligo acid, as the NN stacks differ
+            let mut reaches: [Option<SeedlessReach>; 2] = [None, None];
+
+            for (u, (oligo, best)) in self.oligos.iter().zip(scores.iter_mut()).enumerate() {
+                let (acids, acids_rev) = ((kmer.acid, oligo.acid), (oligo.acid, kmer.acid));
+                // An oligo with no seed is only skipped if every overlap it has with seq is
+                // shorter than the seedless reach, the shortest that could score <= t.
+                // Weighted tails can lower a score below the seedless bound
+                if !seeded[u] && policy.is_unweighted(tails) {
+                    let reach = reaches[(oligo.acid == NucleicAcid::Rna) as usize]
+                        .get_or_insert_with(|| {
+                            SeedlessReach::new(
+                                seq,
+                                &seq_rev,
+                                (kmer.acid, oligo.acid),
+                                kmer.extendable,
+                                max_len,
+                                t,
+                                params,
+                            )
+                        });
+                    if reach.needs_seed(oligo.seq.len()) {
+                        continue;
+                    }
+                }
+                if !policy.is_unweighted(tails) {
+                    let extendable = (kmer.extendable, true);
+                    let score = calc_seqs_min_score_tailed(
+                        seq, &oligo.seq, tails, acids, extendable, &policy, params,
+                    );
+                    if let Some(score) = score.filter(|s| *s <= t) {
+                        *best = Some(best.map_or(score, |b| b.min(score)));
+                    }
+                    continue;
+                }
+                let both = packed_seq.as_ref().zip(oligo.packed.as_ref());
+                // seq extending on the oligo, then the oligo extending on seq
+                let s1_score = match kmer.extendable {
+                    true => seq1_min_score_below(
+                        seq,
+                        &oligo.seq_rev,
+                        both.map(|((fwd, _), (_, rev))| (fwd, rev)),
+                        t,
+                        nn_table(acids),
+                        &BoundTerms::new(acids, params),
+                        params,
+                    ),
+                    false => None,
+                };
+                let s2_score = seq1_min_score_below(
+                    &oligo.seq,
+                    &seq_rev,
+                    both.map(|((_, rev), (fwd, _))| (fwd, rev)),
+                    t,
+                    nn_table(acids_rev),
+                    &BoundTerms::new(acids_rev, params),
+                    params,
+                );
+                for score in [s1_score, s2_score].into_iter().flatten() {
+                    *best = Some(best.map_or(score, |b| b.min(score)));
+                }
+            }
+        }
+
+        let mut hits: Vec<(usize, f64)> = Vec::new();
+        for (oligo, score) in self.oligos.iter().zip(scores) {
+            if let Some(score) = score {
+                hits.extend(oligo.members.iter().map(|index| (*index, score)));
+            }
+        }
+        hits.sort_by_key(|(index, _score)| *index);
+        hits
+    }
+}
+
+/// How long an overlap between a seq and a library oligo must be before they could
+/// score <= t with no run of SEED_LEN matches
+#[derive(Debug, Clone)]
+struct SeedlessReach {
+    seq_len: usize,
+    // With seq extending on the oligo, then the oligo extending on seq.
+    // usize::MAX if no overlap in the library is long enough
+    seq1: usize,
+    seq2: usize,
+}
+
+impl SeedlessReach {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        seq: &[usize],
+        seq_rev: &[usize],
+        acids: (NucleicAcid, NucleicAcid),
+        extendable: bool,
+        max_len: usize,
+        t: f64,
+        params: &ScoringParams,
+    ) -> SeedlessReach {
+        // acids are (seq, oligo)
+        let acids_rev = (acids.1, acids.0);
+        // seq extending covers a suffix of seq
+        let seq1 = match extendable {
+            true => seedless_overlap(
+                seq,
+                seq.len()..=seq.len(),
+                true,
+                seq.len().min(max_len.saturating_sub(1)),
+                (nn_table(acids), &BoundTerms::new(acids, params)),
+                t,
+                params,
+            ),
+            false => usize::MAX,
+        };
+        // The oligo extending covers any window of seq_rev with a base past it
+        let seq2 = seedless_overlap(
+            seq_rev,
+            2..=seq_rev.len().saturating_sub(1),
+            false,
+            seq_rev.len().saturating_sub(1).min(max_len),
+            (nn_table(acids_rev), &BoundTerms::new(acids_rev, params)),
+            t,
+            params,
+        );
+        SeedlessReach {
+            seq_len: seq.len(),
+            seq1,
+            seq2,
+        }
+    }
+
+    fn needs_seed(&self, oligo_len: usize) -> bool {
+        // The longest overlap of seq1 extending on seq2
+        let overlap = |len1: usize, len2: usize| len1.min(len2.saturating_sub(1));
+        overlap(self.seq_len, oligo_len) < self.seq1 && overlap(oligo_len, self.seq_len) < self.seq2
+    }
+}
+
+fn stack_minima(table: &NnTable, known_seq1: bool, params: &ScoringParams) -> [[[f64; 4]; 4]; 4] {
+    // The lowest NN stack for each dinucleotide of the known seq, by whether its two
+    // positions match (2 * first + second), over every dinucleotide of the other seq
+    let mut minima = [[[f64::INFINITY; 4]; 4]; 4];
+    for (a, b, c, d) in (0..4).flat_map(|a| {
+        (0..4).flat_map(move |b| (0..4).flat_map(move |c| (0..4).map(move |d| (a, b, c, d))))
+    }) {
+        let (s1, s2) = match known_seq1 {
+            true => ((a, b), (c, d)),
+            false => ((c, d), (a, b)),
+        };
+        let matches = 2 * MATCH_ARRAY[s1.0][s2.0] as usize + MATCH_ARRAY[s1.1][s2.1] as usize;
+        let score = table[s1.0][s1.1][s2.0][s2.1].unwrap_or(params.bonus[0]);
+        minima[a][b][matches] = minima[a][b][matches].min(score);
+    }
+    minima
+}
+
+fn seedless_overlap(
+    known: &[usize],
+    ends: RangeInclusive<usize>,
+    known_seq1: bool,
+    max_overlap: usize,
+    (table, terms): (&NnTable, &BoundTerms),
+    t: f64,
+    params: &ScoringParams,
+) -> usize {
+    // The shortest overlap known[end - n..end], for any end, that could score <= t against
+    // any other seq with no run of SEED_LEN matches. usize::MAX if none up to max_overlap.
+    // known is seq1, or seq2 reversed. Each mask is built from the 3' end, with the exact
+    // extension score, the match bonuses of offset_lower_bound, and the lowest NN stacks
+    // given the known bases
+    let minima = stack_minima(table, known_seq1, params);
+    // The longest match is between 1 and SEED_LEN - 1
+    let longest_match = -(params.bonus[7]).max((SEED_LEN - 1) as f64 * params.bonus[7]);
+    let fixed = terms.dangling_ends + longest_match;
+    let bubble = -params.bonus[0] * params.bonus[9];
+    // States are a run of 1..SEED_LEN matches, then a run of 1, 2 or 3+ mismatches
+    let n_states = SEED_LEN + 2;
+    let state = |is_match: bool, run: usize| match is_match {
+        true => run - 1,
+        false => SEED_LEN - 2 + run.min(3),
+    };
+    let runs: Vec<(bool, usize)> = (1..SEED_LEN)
+        .map(|run| (true, run))
+        .chain((1..=3).map(|run| (false, run)))
+        .collect();
+
+    let mut reach = usize::MAX;
+    for end in ends {
+        let limit = max_overlap.min(end).min(reach.saturating_sub(1));
+        if limit < 2 {
+            continue;
+        }
+        // costs[state][matches]
+        let mut costs = vec![vec![f64::INFINITY; limit + 1]; n_states];
+        for n in 1..=limit {
+            let pos = end - n;
+            // calc_extention, as the other seq's base complements a known base that matches
+            let extension = match (n <= 4, known[pos]) {
+                (true, 1 | 2) => -3. / n as f64,
+                (true, _) => -2. / n as f64,
+                (false, _) => 0.,
+            };
+            let mut next = vec![vec![f64::INFINITY; limit + 1]; n_states];
+            if n == 1 {
+                next[state(true, 1)][1] = extension;
+                next[state(false, 1)][0] = 0.;
+            }
+            for (from, (was_match, run)) in runs.iter().enumerate() {
+                for (matches, cost) in costs[from].iter().enumerate() {
+                    if cost.is_infinite() {
+                        continue;
+                    }
+                    for is_match in [true, false] {
+                        let run = match is_match == *was_match {
+                            true => run + 1,
+                            false => 1,
+                        };
+                        if is_match && run >= SEED_LEN {
+                            continue;
+                        }
+                        let stack = 2 * is_match as usize + *was_match as usize;
+                        let mut cost = cost + minima[known[pos]][known[pos + 1]][stack];
+                        match is_match {
+                            true => cost += extension,
+                            false if run >= 3 => cost += bubble,
+                            false => (),
+                        }
+                        let (to, matches) = (state(is_match, run), matches + is_match as usize);
+                        next[to][matches] = next[to][matches].min(cost);
+                    }
+                }
+            }
+            // The final two 3' bases can't both mismatch
+            if n == 2 {
+                next[state(false, 2)].fill(f64::INFINITY);
+            }
+            costs = next;
+            // When every base up to the fourth matches
+            if n == 4 && SEED_LEN > 4 {
+                costs[state(true, 4)]
+                    .iter_mut()
+                    .for_each(|cost| *cost -= 2.);
+            }
+            if n < 2 {
+                continue;
+            }
+            let lowest = costs
+                .iter()
+                .enumerate()
+                .flat_map(|(s, row)| row.iter().enumerate().map(move |(m, c)| (s, m, c)))
+                .map(|(s, matches, cost)| {
+                    let all_match = match n < 4 && runs[s] == (true, n) {
+                        true => -2.,
+                        false => 0.,
+                    };
+                    cost + calc_match_prop_bonus(matches, n, params) + all_match
+                })
+                .fold(f64::INFINITY, f64::min);
+            if fixed + lowest <= t {
+                reach = reach.min(n);
+                break;
+            }
+        }
+    }
+    reach
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{calc_kmers_min_score, encode_base, DEFAULT_PARAMS};
+    use super::*;
+    use itertools::Itertools;
+    use proptest::prelude::*;
+    const DNA: NucleicAcid = NucleicAcid::Dna;
+    const RNA: NucleicAcid = NucleicAcid::Rna;
+    fn pairwise(
+        library: &[(Vec<usize>, NucleicAcid)],
+        kmer: &KmerSeqs,
+        t: f64,
+    ) -> Vec<(usize, f64)> {
+        library
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (oligo, acid))| {
+                let oligo = [oligo.clone()];
+                let mut oligo = KmerSeqs::new(&oligo);
+                oligo.acid = *acid;
+                let score =
+                    calc_kmers_min_score(kmer, &oligo, &TailPolicy::default(), &DEFAULT_PARAMS);
+                score.map(|s| (i, s))
+            })
+            .filter(|(_i, score)| *score <= t)
+            .collect()
+    }
+    #[test]
+    fn test_screen_matches_pairwise() {
+        let long = "ACGTTGCA".repeat(20);
+        let library: Vec<(Vec<usize>, NucleicAcid)> = [
+            "TGGAAATACCCACAAGTTAATGGTTTAAC",
+            "GGCACAGGTGTAAA",
+            "AATGATACGGCGACCACCGA",
+            "GGCACAGGTGTAAA",
+            long.as_str(),
+            "CAAGCAGAAGACGGCATACGA",
+            "GGCACAGGUGUAAA",
+        ]
+        .iter()
+        .map(|s| (encode_base(s), NucleicAcid::of(s)))
+        .collect();
+        let index = OligoLibrary::new(&library);
+        // The RNA copy is scored apart from the DNA
+        assert_eq!((index.len(), index.n_unique()), (7, 6));
+
+        let primers: Vec<Vec<usize>> = ["ACACCTGTGCCTGTTAAACCAT", "TTTACACCTGTGCC", "ACGTTGCAACGT"]
+            .iter()
+            .map(|s| encode_base(s))
+            .collect();
+        // Each primer alone, then as one kmer
+        let kmers = [&primers[0..1], &primers[1..2], &primers[2..3], &primers[..]];
+        for seqs in kmers {
+            let cases = [true, false].into_iter().cartesian_product([DNA, RNA]);
+            for ((extendable, acid), tail_len) in cases.cartesian_product([0, 8]) {
+                let kmer = KmerSeqs {
+                    seqs,
+                    extendable,
+                    acid,
+                    tail_len,
+                };
+                for t in [-26., -10.] {
+                    let expected = pairwise(&library, &kmer, t);
+                    assert_eq!(index.screen(&kmer, t, &DEFAULT_PARAMS), expected);
+                }
+            }
+        }
+    }
+    #[test]
+    fn test_empty_library() {
+        let index = OligoLibrary::new(&[]);
+        assert!(index.is_empty());
+        let seqs = [encode_base("ACGTAC")];
+        assert!(index
+            .screen(&KmerSeqs::new(&seqs), -26., &DEFAULT_PARAMS)
+            .is_empty());
+    }
+    #[test]
+    fn test_seedless_reach() {
+        // A primer can't reach -26 on a barcode without a run of SEED_LEN matches
+        let seq = encode_base("CCAAACAAAGTTGGGTAAGGATAGATCAAT");
+        let seq_rev: Vec<usize> = seq.iter().rev().copied().collect();
+        let reach = SeedlessReach::new(&seq, &seq_rev, (DNA, DNA), true, 12, -26., &DEFAULT_PARAMS);
+        assert!(reach.needs_seed(12));
+        // But it can on a long enough oligo
+        let reach = SeedlessReach::new(&seq, &seq_rev, (DNA, DNA), true, 60, -26., &DEFAULT_PARAMS);
+        assert!(!reach.needs_seed(60));
+    }
+    proptest! {
+        #[test]
+        fn prop_screen_short_oligos(
+            library in prop::collection::vec(
+                (prop::collection::vec(0..4usize, 2..16), any::<bool>()), 1..12
+            ),
+            seq in prop::collection::vec(0..4usize, 2..30),
+            rna in any::<bool>(),
+            extendable in any::<bool>(),
+            t in -30.0..-5.0f64,
+        ) {
+            // Oligos skipped for having no seed can't score <= t
+            let library: Vec<(Vec<usize>, NucleicAcid)> = library
+                .into_iter()
+                .map(|(oligo, rna)| (oligo, if rna { RNA } else { DNA }))
+                .collect();
+            let seqs = [seq];
+            let mut kmer = KmerSeqs::new(&seqs);
+            kmer.extendable = extendable;
+            kmer.acid = if rna { RNA } else { DNA };
+            let index = OligoLibrary::new(&library);
+            prop_assert_eq!(index.screen(&kmer, t, &DEFAULT_PARAMS), pairwise(&library, &kmer, t));
+        }
+    }
+}
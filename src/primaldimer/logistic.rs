@@ -0,0 +1,96 @@
+// Logistic mapping of the raw score onto a dimer probability
+// p = 1 / (1 + exp(-(intercept + slope * score)))
+//
+// No coefficients are packaged, as there is no labelled data to calibrate them against.
+// They come from fit_logistic, or fit_params, on the user's own labelled pairs
+// 0 = INTERCEPT
+// 1 = SLOPE
+
+// Ridge penalty for fit_logistic, which keeps it finite when the labels are separable
+const RIDGE: f64 = 1e-3;
+
+pub fn logistic(coefs: &[f64; 2], score: f64) -> f64 {
+    let logit = coefs[0] + coefs[1] * score;
+    1. / (1. + (-logit).exp())
+}
+
+pub fn fit_logistic(scores: &[f64], labels: &[bool]) -> [f64; 2] {
+    // Fit the intercept and slope to the labelled scores by Newton's method on the log loss
+    let mut coefs = [0., 0.];
+    for _ in 0..50 {
+        // Gradient and Hessian of the penalised loss
+        let mut grad = [RIDGE * coefs[0], RIDGE * coefs[1]];
+        let mut hess = [[RIDGE, 0.], [0., RIDGE]];
+        for (score, label) in scores.iter().zip(labels.iter()) {
+            let p = logistic(&coefs, *score);
+            let residual = p - *label as u8 as f64;
+            let weight = p * (1. - p);
+            grad[0] += residual;
+            grad[1] += residual * score;
+            hess[0][0] += weight;
+            hess[0][1] += weight * score;
+            hess[1][1] += weight * score * score;
+        }
+        let det = hess[0][0] * hess[1][1] - hess[0][1] * hess[0][1];
+        let step = [
+            (hess[1][1] * grad[0] - hess[0][1] * grad[1]) / det,
+            (hess[0][0] * grad[1] - hess[0][1] * grad[0]) / det,
+        ];
+        coefs[0] -= step[0];
+        coefs[1] -= step[1];
+        if step[0].abs() < 1e-9 && step[1].abs() < 1e-9 {
+            break;
+        }
+    }
+    coefs
+}
+
+pub fn probability_to_score(coefs: &[f64; 2], p: f64) -> Option<f64> {
+    // The inverse of logistic. None unless 0 < p < 1, or if the slope is 0
+    // With a negative slope, a score <= probability_to_score(p) means a probability >= p
+    if p <= 0. || p >= 1. || p.is_nan() || coefs[1] == 0. {
+        return None;
+    }
+    Some(((p / (1. - p)).ln() - coefs[0]) / coefs[1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    // p = 0.5 at -26, with a negative slope
+    const COEFS: [f64; 2] = [-7.8, -0.3];
+    #[test]
+    fn test_midpoint() {
+        assert!((logistic(&COEFS, -26.) - 0.5).abs() < 1e-9);
+        assert!((probability_to_score(&COEFS, 0.5).unwrap() - -26.).abs() < 1e-9);
+    }
+    #[test]
+    fn test_monotonic() {
+        // Lower scores are more stable so should be more likely to form a dimer
+        assert!(logistic(&COEFS, -40.) > logistic(&COEFS, -30.));
+        assert!(logistic(&COEFS, -30.) > logistic(&COEFS, -10.));
+    }
+    #[test]
+    fn test_round_trip() {
+        for score in [-45., -26., -12.5, 0.] {
+            let p = logistic(&COEFS, score);
+            assert!((probability_to_score(&COEFS, p).unwrap() - score).abs() < 1e-6);
+        }
+    }
+    #[test]
+    fn test_fit_logistic() {
+        // Dimers are more likely at lower scores, so the fitted slope is negative
+        let scores = [-40., -35., -30., -27., -25., -20., -15., -10.];
+        let labels = [true, true, false, true, false, true, false, false];
+        let coefs = fit_logistic(&scores, &labels);
+        assert!(coefs[1] < 0.);
+        assert!(logistic(&coefs, -40.) > logistic(&coefs, -10.));
+    }
+    #[test]
+    fn test_invalid_probability() {
+        for p in [0., 1., -0.5, 2., f64::NAN] {
+            assert_eq!(probability_to_score(&COEFS, p), None);
+        }
+        assert_eq!(probability_to_score(&[0., 0.], 0.5), None);
+    }
+}
@@ -1,30 +1,51 @@
+mod alternatives;
+mod amplicons;
+mod background;
+mod bits;
+mod cache;
+mod evaluation;
+mod graph;
+mod heatmap;
+mod library;
+mod logistic;
+mod params;
+mod product;
+mod reference;
+mod rna;
 mod scores;
-use scores::{MATCH_ARRAY, NN_SCORES, SEQ1_OVERHANG_ARRAY, SEQ2_OVERHANG_ARRAY};
+mod tails;
+mod tm;
+mod training;
+mod variants;
+pub use alternatives::suggest_shifted;
+pub use amplicons::{find_short_products, in_silico_pcr, Site, Strand};
+pub use background::BackgroundModel;
+pub use cache::PairCache;
+pub use evaluation::evaluate_thresholds;
+pub use graph::{InteractionGraph, NodeAttrs};
+pub use heatmap::{render_heatmap, score_matrix};
+pub use library::OligoLibrary;
+pub use logistic::{logistic, probability_to_score};
+pub use params::{ScoringParams, DEFAULT_PARAMS};
+pub use product::predict_dimer_product;
+pub use reference::{
+    encode_reference, format_alignment, read_alignment, read_fasta, scan_reference_acids,
+    BindingSite, FastaRecord,
+};
+use rna::nn_table;
+pub use rna::NucleicAcid;
+use scores::{NnTable, MATCH_ARRAY, NN_SCORES, SEQ1_OVERHANG_ARRAY, SEQ2_OVERHANG_ARRAY};
+use tails::calc_at_offset_tailed;
+pub use tails::{calc_seqs_min_score_tailed, TailPolicy};
+pub use tm::{calc_tm, calc_tm_hybrid};
+pub use training::{fit_params, EncodedPair};
+pub use variants::calc_genome_binding;
 
+use bits::{match_mask, range_mask, MatchRuns, PackedSeq};
 use itertools::Itertools;
-
-static BONUS_ARRAY: [f64; 10] = [
-    1.11217618,
-    0.55187469,
-    1.01582516,
-    1.03180592,
-    -2.76687727,
-    -0.81903133,
-    0.93596145,
-    2.32758405,
-    3.24507248,
-    0.80416919,
-];
-// 0 = PENALTY_DOUBLE_MISMATCH
-// 1 = PENALTY_LEFT_OVERHANG_MISMATCH
-// 2 = PENALTY_RIGHT_OVERHANG_MISMATCH
-// 3 = BONUS_ALL_MATCH
-// 4 = BONUS_3P_MATCH_GC
-// 5 = BONUS_3P_MATCH_AT
-// 6 = SCORE_3P_MISMATCH
-// 7 = LONGEST_MATCH_COEF
-// 8 = MATCH_PROP_COEF
-// 9 = BUBBLE_COEF
+use std::fmt;
+use std::ops::Range;
+use std::sync::OnceLock;
 
 //base_to_u8 = {"A": 65, "T": 84, "C": 67, "G": 71}
 
@@ -38,10 +59,12 @@ pub fn encode_base(sequence: &str) -> Vec<usize> {
             84 => 3,
             67 => 1,
             71 => 2,
+            // RNA. U pairs as T
+            85 => 3,
             _ => panic!("NON STANDRD BASE found in {}", sequence),
         })
         .collect();
-    return encoded_base;
+    encoded_base
 }
 
 pub fn decode_base(encoded_base: &[usize]) -> String {
@@ -56,54 +79,75 @@ pub fn decode_base(encoded_base: &[usize]) -> String {
         })
         .collect::<Vec<&str>>()
         .join("");
-    return decoded_base;
+    decoded_base
 }
 
 fn calc_dangling_ends_stabilty(
     seq1: &[usize],
     seq2: &[usize],
-    mapping: &Vec<(usize, usize)>,
+    left: (usize, usize),
+    right: (usize, usize),
+    params: &ScoringParams,
 ) -> f64 {
+    // left and right are the (seq2_i, seq1_i) of the first and last aligned positions
     let mut dg_score = 0.;
 
     // Look for overhang on the right side
-    let (seq2_i, seq1_i) = mapping[mapping.len() - 1];
+    let (seq2_i, seq1_i) = right;
 
     match SEQ2_OVERHANG_ARRAY[seq1[seq1_i]][seq2[seq2_i]][seq2[seq2_i + 1]] {
         Some(score) => dg_score += score,
-        None => dg_score += BONUS_ARRAY[2],
+        None => dg_score += params.bonus[2],
     }
 
     // Look for overhang on the leftside
-    let (seq2_i, seq1_i) = mapping[0];
+    let (seq2_i, seq1_i) = left;
 
     if seq1_i > 0 {
         match SEQ1_OVERHANG_ARRAY[seq1[seq1_i]][seq2[seq2_i]][seq1[seq1_i - 1]] {
             Some(score) => dg_score += score,
-            None => dg_score += BONUS_ARRAY[1],
+            None => dg_score += params.bonus[1],
         }
     } else if seq2_i > 0 {
         match SEQ2_OVERHANG_ARRAY[seq1[seq1_i]][seq2[seq2_i]][seq2[seq2_i - 1]] {
             Some(score) => dg_score += score,
-            None => dg_score += BONUS_ARRAY[1],
+            None => dg_score += params.bonus[1],
         }
     }
 
-    return dg_score;
+    dg_score
+}
+
+fn calc_table_nn_stack(
+    table: &NnTable,
+    seq1: &[usize],
+    seq2: &[usize],
+    seq2_i: usize,
+    seq1_i: usize,
+    params: &ScoringParams,
+) -> f64 {
+    match table[seq1[seq1_i]][seq1[seq1_i + 1]][seq2[seq2_i]][seq2[seq2_i + 1]] {
+        Some(score) => score,    // If match or single mismatch
+        None => params.bonus[0], // If Double mismatch
+    }
 }
 
-fn calc_nn_thermo(seq1: &[usize], seq2: &[usize], mapping: &Vec<(usize, usize)>) -> f64 {
+fn calc_nn_thermo(
+    table: &NnTable,
+    seq1: &[usize],
+    seq2: &[usize],
+    stacks: impl Iterator<Item = (usize, usize)>,
+    params: &ScoringParams,
+) -> f64 {
+    // The sum of the NN stacks starting at each (seq2_i, seq1_i)
     let mut dg_score: f64 = 0.;
-    for (seq2_i, seq1_i) in mapping.iter() {
-        match NN_SCORES[seq1[*seq1_i]][seq1[*seq1_i + 1]][seq2[*seq2_i]][seq2[*seq2_i + 1]] {
-            Some(score) => dg_score += score,   // If match or single mismatch
-            None => dg_score += BONUS_ARRAY[0], // If Double mismatch
-        }
+    for (seq2_i, seq1_i) in stacks {
+        dg_score += calc_table_nn_stack(table, seq1, seq2, seq2_i, seq1_i, params);
     }
-    return dg_score;
+    dg_score
 }
 
-fn calc_extention(seq1: &[usize], match_bool: &Vec<bool>) -> Option<f64> {
+fn calc_extention(seq1: &[usize], match_bool: &[bool]) -> Option<f64> {
     // Guard for no matches in final two 3' bases
     if !match_bool[match_bool.len() - 2..].iter().any(|f| *f) {
         return None;
@@ -111,19 +155,14 @@ fn calc_extention(seq1: &[usize], match_bool: &Vec<bool>) -> Option<f64> {
 
     let mut score: f64 = 0.;
 
-    let kmer_3p_bool: Vec<(usize, &bool)> = match_bool
-        .iter()
-        .rev()
-        .enumerate()
-        .filter(|(index, _bool)| *index < 4)
-        .collect();
+    let kmer_3p_bool = &match_bool[match_bool.len().saturating_sub(4)..];
 
     // Look at the last 4 bases in the match
 
-    for (index, match_bool) in kmer_3p_bool.iter() {
+    for (index, match_bool) in kmer_3p_bool.iter().rev().enumerate() {
         let seq1_index = seq1.len() - 1 - index;
         // Only count matches
-        if **match_bool {
+        if *match_bool {
             // Add match score
             match seq1[seq1_index] {
                 1 | 2 => score += 3. * (1. / (index + 1) as f64), // CG match
@@ -133,66 +172,98 @@ fn calc_extention(seq1: &[usize], match_bool: &Vec<bool>) -> Option<f64> {
         }
     }
 
-    if kmer_3p_bool.iter().all(|(_index, bool)| **bool) {
+    if kmer_3p_bool.iter().all(|bool| *bool) {
         score += 2.;
     }
 
-    return Some(-score);
+    Some(-score)
 }
 
-fn apply_bonus(match_bool: &Vec<bool>) -> f64 {
-    // Find the longest continous match
-    let mut current_match = 0;
-    let mut longest_match = 0;
-
-    for m in match_bool.iter() {
-        match m {
-            true => current_match += 1,
-            false => current_match = 0,
-        }
-        if current_match > longest_match {
-            longest_match = current_match
-        }
-    }
-    let mut score = 0.;
-
-    // Find proportion of matches
-    score += -((0.8
-        - (match_bool.iter().filter(|b| **b).count() as f64 / match_bool.len() as f64))
-        * BONUS_ARRAY[8]);
+fn calc_match_prop_bonus(matches: usize, len: usize, params: &ScoringParams) -> f64 {
+    -((0.8 - (matches as f64 / len as f64)) * params.bonus[8])
+}
 
-    // Group the match bool
-    let grouped_match_bool: Vec<(bool, usize)> = match_bool
+fn group_match_bool(match_bool: &[bool]) -> Vec<(bool, usize)> {
+    match_bool
         .iter()
         .group_by(|bool| **bool)
         .into_iter()
         .map(|(bool, iter)| (bool, iter.count()))
-        .collect();
+        .collect()
+}
 
-    // Work out the longest match
+fn calc_longest_match_bonus(
+    grouped_match_bool: impl Iterator<Item = (bool, usize)>,
+    params: &ScoringParams,
+) -> f64 {
     let longest_match = grouped_match_bool
-        .iter()
         .filter(|(bool, _count)| *bool)
         .map(|(_bool, count)| count)
         .max();
 
     match longest_match {
-        Some(max) => score += -(*max as f64 * BONUS_ARRAY[7]),
-        None => (),
+        Some(max) => -(max as f64 * params.bonus[7]),
+        None => 0.,
     }
+}
+
+fn calc_bubble_penalty(count: usize, params: &ScoringParams) -> f64 {
+    -((count as f64 - 2.) * params.bonus[0]) * params.bonus[9]
+}
+
+/// The match bonuses of an offset, and their total
+struct BonusTerms {
+    match_prop: f64,
+    longest_match: f64,
+    bubbles: f64,
+    total: f64,
+}
+
+fn bonus_terms(
+    matches: usize,
+    len: usize,
+    grouped_match_bool: impl Iterator<Item = (bool, usize)> + Clone,
+    params: &ScoringParams,
+) -> BonusTerms {
+    // Find proportion of matches
+    let match_prop = calc_match_prop_bonus(matches, len, params);
+
+    // Work out the longest match
+    let longest_match = calc_longest_match_bonus(grouped_match_bool.clone(), params);
+
+    // The total adds each term in turn, as the score always has
+    let mut total = 0.;
+    total += match_prop;
+    total += longest_match;
 
     // Resolve bubbles
-    for (match_bool, count) in grouped_match_bool.iter() {
-        if !*match_bool && count > &2 {
-            score += -((*count as f64 - 2.) * BONUS_ARRAY[0]) * BONUS_ARRAY[9]
+    let mut bubbles = 0.;
+    for (match_bool, count) in grouped_match_bool {
+        if !match_bool && count > 2 {
+            let penalty = calc_bubble_penalty(count, params);
+            bubbles += penalty;
+            total += penalty;
         }
     }
 
-    return score;
+    BonusTerms {
+        match_prop,
+        longest_match,
+        bubbles,
+        total,
+    }
 }
 
-pub fn calc_at_offset(seq1: &[usize], seq2: &[usize], offset: i32) -> Option<f64> {
-    // Create the mapping
+fn sum_bonus(
+    matches: usize,
+    len: usize,
+    grouped_match_bool: impl Iterator<Item = (bool, usize)> + Clone,
+    params: &ScoringParams,
+) -> f64 {
+    bonus_terms(matches, len, grouped_match_bool, params).total
+}
+
+fn create_mapping(seq1: &[usize], offset: i32) -> Vec<(usize, usize)> {
     let mut mapping: Vec<(usize, usize)> = Vec::new();
     for x in 0..seq1.len() {
         let seq2_index = x as i32 + offset;
@@ -200,72 +271,743 @@ pub fn calc_at_offset(seq1: &[usize], seq2: &[usize], offset: i32) -> Option<f64
             mapping.push((seq2_index as usize, x))
         }
     }
+    mapping
+}
+
+fn offset_in_range(seq1: &[usize], seq2: &[usize], offset: i32) -> bool {
+    // seq1 can only extend if at least its final two 3' bases are paired,
+    // and seq2 has a base past seq1's 3' end to extend onto
+    let (len1, len2, offset) = (seq1.len() as i64, seq2.len() as i64, offset as i64);
+    len1 >= 2 && offset >= -(len1 - 2) && len1 + offset < len2
+}
+
+/// Why a pair of seqs could not be scored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoreError {
+    // A base outside of the A, C, G, T encoding. seq is 1 or 2
+    InvalidBase {
+        seq: usize,
+        index: usize,
+        base: usize,
+    },
+}
+impl fmt::Display for ScoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScoreError::InvalidBase { seq, index, base } => write!(
+                f,
+                "Invalid encoded base {} at index {} of seq{}",
+                base, index, seq
+            ),
+        }
+    }
+}
+impl std::error::Error for ScoreError {}
+
+pub fn try_calc_at_offset(
+    seq1: &[usize],
+    seq2: &[usize],
+    offset: i32,
+    acids: (NucleicAcid, NucleicAcid),
+    tails: (usize, usize),
+    policy: &TailPolicy,
+    params: &ScoringParams,
+) -> Result<Option<f64>, ScoreError> {
+    // calc_at_offset_tailed for seqs that may not be encoded with encode_base, with the NN
+    // stacks of seq1 (acids.0) on seq2 (acids.1)
+    for (seq, bases) in [(1, seq1), (2, seq2)] {
+        if let Some((index, base)) = bases.iter().enumerate().find(|(_i, b)| **b > 3) {
+            return Err(ScoreError::InvalidBase {
+                seq,
+                index,
+                base: *base,
+            });
+        }
+    }
+    let table = nn_table(acids);
+    Ok(match policy.is_unweighted(tails) {
+        true => calc_at_offset_table(seq1, seq2, offset, table, params),
+        false => calc_at_offset_tailed(seq1, seq2, tails, offset, policy, table, params),
+    })
+}
+
+fn calc_at_offset_table(
+    seq1: &[usize],
+    seq2: &[usize],
+    offset: i32,
+    table: &NnTable,
+    params: &ScoringParams,
+) -> Option<f64> {
+    // The score of seq1 (5'-3') on seq2 (3'-5') at offset, with the NN stacks from table,
+    // such as the RNA tables from nn_table. Any length of seq and any offset is valid.
+    // Those where seq1 can't extend are None. Untailed, so it scores as
+    // calc_at_offset_breakdown_table without building the mapping
+    if !offset_in_range(seq1, seq2, offset) {
+        return None;
+    }
+    let first = (-offset).max(0) as usize;
+    let end = seq1.len();
+    let seq2_index = |x: usize| (x as i32 + offset) as usize;
+    let is_match = |x: usize| MATCH_ARRAY[seq1[x]][seq2[seq2_index(x)]];
+
+    let mut dg_score = calc_dangling_ends_stabilty(
+        seq1,
+        seq2,
+        (seq2_index(first), first),
+        (seq2_index(end - 1), end - 1),
+        params,
+    );
+
+    // calc_extention only looks at the final four positions
+    let tail_len = (end - first).min(4);
+    let mut tail = [false; 4];
+    for (i, tail_match) in tail[..tail_len].iter_mut().enumerate() {
+        *tail_match = is_match(end - tail_len + i);
+    }
+    dg_score += calc_extention(seq1, &tail[..tail_len])?;
+
+    let runs = (first..end)
+        .map(is_match)
+        .dedup_with_count()
+        .map(|(count, is_match)| (is_match, count));
+    dg_score += sum_bonus(
+        (first..end).filter(|x| is_match(*x)).count(),
+        end - first,
+        runs,
+        params,
+    );
+
+    // The 3' position has no NN stack
+    let stacks = (first..end - 1).map(|x| (seq2_index(x), x));
+    dg_score += calc_nn_thermo(table, seq1, seq2, stacks, params);
+
+    Some(dg_score)
+}
+
+/// The score contribution of a single aligned position
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionScore {
+    pub seq1_index: usize,
+    pub seq2_index: usize,
+    pub is_match: bool,
+    // The NN stack between this position and the next. None for the 3' position
+    pub nn_score: Option<f64>,
+}
+
+/// The individual terms that are summed by `calc_at_offset`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreBreakdown {
+    pub score: f64,
+    pub dangling_ends: f64,
+    pub extension: f64,
+    pub match_prop: f64,
+    pub longest_match: f64,
+    pub bubbles: f64,
+    pub nn_stack: f64,
+    pub positions: Option<Vec<PositionScore>>,
+}
+
+pub fn calc_at_offset_breakdown(
+    seq1: &[usize],
+    seq2: &[usize],
+    offset: i32,
+    acids: (NucleicAcid, NucleicAcid),
+    per_position: bool,
+    params: &ScoringParams,
+) -> Option<ScoreBreakdown> {
+    // Each term of calc_at_offset, which sum to its score. The NN stacks are those of
+    // seq1 (acids.0) on seq2 (acids.1)
+    let table = nn_table(acids);
+    let no_tails = TailPolicy::default();
+    calc_at_offset_breakdown_table(
+        seq1,
+        seq2,
+        offset,
+        table,
+        (0, 0),
+        &no_tails,
+        per_position,
+        params,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn calc_at_offset_breakdown_table(
+    seq1: &[usize],
+    seq2: &[usize],
+    offset: i32,
+    table: &NnTable,
+    tails: (usize, usize),
+    policy: &TailPolicy,
+    per_position: bool,
+    params: &ScoringParams,
+) -> Option<ScoreBreakdown> {
+    // The scoring of every offset. calc_at_offset only keeps the score.
+    // The first tails.0 bases of seq1 and the last tails.1 of seq2 (its 5' end, as seq2 is
+    // reversed) are weighted by policy. Bases at a position with a weight of 0 can't pair,
+    // and each NN stack is scaled by the lower weight of its two positions. The other terms
+    // are not weighted
+    if !offset_in_range(seq1, seq2, offset) {
+        return None;
+    }
+
+    // Create the mapping
+    let mapping = create_mapping(seq1, offset);
+    let seq2_tail_start = seq2.len().saturating_sub(tails.1);
+    let weights: Vec<f64> = mapping
+        .iter()
+        .map(|(seq2_i, seq1_i)| policy.weight(*seq1_i < tails.0, *seq2_i >= seq2_tail_start))
+        .collect();
 
     // Create the match_bool
-    let match_bool = mapping
+    let match_bool: Vec<bool> = mapping
         .iter()
-        .map(|(seq2i, seq1i)| MATCH_ARRAY[seq1[*seq1i] as usize][seq2[*seq2i] as usize])
+        .zip(weights.iter())
+        .map(|((seq2i, seq1i), w)| MATCH_ARRAY[seq1[*seq1i]][seq2[*seq2i]] && *w != 0.)
         .collect();
 
-    let mut dg_score = calc_dangling_ends_stabilty(&seq1, &seq2, &mapping);
+    let dangling_ends =
+        calc_dangling_ends_stabilty(seq1, seq2, mapping[0], mapping[mapping.len() - 1], params);
+    let extension = calc_extention(seq1, &match_bool)?;
+
+    // Apply longest match, and match proportion
+    let bonus = bonus_terms(
+        match_bool.iter().filter(|b| **b).count(),
+        match_bool.len(),
+        group_match_bool(&match_bool).into_iter(),
+        params,
+    );
+
+    // The 3' position has no NN stack
+    let stacks = &mapping[..mapping.len() - 1];
+    let stack_score = |i: usize| {
+        let (seq2_i, seq1_i) = stacks[i];
+        weights[i].min(weights[i + 1])
+            * calc_table_nn_stack(table, seq1, seq2, seq2_i, seq1_i, params)
+    };
+    let mut nn_stack: f64 = 0.;
+    for i in 0..stacks.len() {
+        nn_stack += stack_score(i);
+    }
+
+    let mut score = dangling_ends;
+    score += extension;
+    score += bonus.total;
+    score += nn_stack;
+
+    let positions = match per_position {
+        true => Some(
+            mapping
+                .iter()
+                .zip(match_bool.iter())
+                .enumerate()
+                .map(|(i, ((seq2_i, seq1_i), is_match))| PositionScore {
+                    seq1_index: *seq1_i,
+                    seq2_index: *seq2_i,
+                    is_match: *is_match,
+                    nn_score: (i < stacks.len()).then(|| stack_score(i)),
+                })
+                .collect(),
+        ),
+        false => None,
+    };
+
+    Some(ScoreBreakdown {
+        score,
+        dangling_ends,
+        extension,
+        match_prop: bonus.match_prop,
+        longest_match: bonus.longest_match,
+        bubbles: bonus.bubbles,
+        nn_stack,
+        positions,
+    })
+}
+
+// Parameter free minima of the score tables, used to bound the score at an offset
+struct TableMinima {
+    seq1_overhang: f64,
+    seq2_overhang: f64,
+    // The lowest NN stack with 0, 1 or 2 mismatched positions,
+    // and if any are a double mismatch that takes the penalty
+    nn_stack: [f64; 3],
+    nn_penalty: [bool; 3],
+}
+
+fn table_minima(acids: (NucleicAcid, NucleicAcid)) -> &'static TableMinima {
+    // One per NN table from nn_table
+    static MINIMA: [OnceLock<TableMinima>; 4] = [const { OnceLock::new() }; 4];
+    let index = 2 * (acids.0 == NucleicAcid::Rna) as usize + (acids.1 == NucleicAcid::Rna) as usize;
+    MINIMA[index].get_or_init(|| {
+        let table = nn_table(acids);
+        let table_min = |table: &[[[Option<f64>; 4]; 4]; 4]| {
+            table
+                .iter()
+                .flatten()
+                .flatten()
+                .flatten()
+                .fold(f64::INFINITY, |a, b| a.min(*b))
+        };
+        let mut minima = TableMinima {
+            seq1_overhang: table_min(&SEQ1_OVERHANG_ARRAY),
+            seq2_overhang: table_min(&SEQ2_OVERHANG_ARRAY),
+            nn_stack: [f64::INFINITY; 3],
+            nn_penalty: [false; 3],
+        };
+        for (a, b, c, d) in (0..4).flat_map(|a| {
+            (0..4).flat_map(move |b| (0..4).flat_map(move |c| (0..4).map(move |d| (a, b, c, d))))
+        }) {
+            let mismatches = !MATCH_ARRAY[a][c] as usize + !MATCH_ARRAY[b][d] as usize;
+            match table[a][b][c][d] {
+                Some(score) => minima.nn_stack[mismatches] = minima.nn_stack[mismatches].min(score),
+                None => minima.nn_penalty[mismatches] = true,
+            }
+        }
+        minima
+    })
+}
+
+// The lowest calc_extention score, with every 3' base matching as G/C
+const MIN_EXTENSION: f64 = -(3. + 1.5 + 1. + 0.75 + 2.);
+
+/// The terms of offset_lower_bound that only depend on the params
+struct BoundTerms {
+    // The lowest dangling ends score
+    dangling_ends: f64,
+    // The lowest dangling ends and extension scores
+    fixed: f64,
+    // The lowest NN stack with 0, 1 or 2 mismatched positions
+    nn_stack: [f64; 3],
+}
+
+impl BoundTerms {
+    fn new(acids: (NucleicAcid, NucleicAcid), params: &ScoringParams) -> BoundTerms {
+        let minima = table_minima(acids);
+        let mut dangling_ends = minima.seq2_overhang.min(params.bonus[2]);
+        // The left side may have no overhang
+        dangling_ends += minima
+            .seq1_overhang
+            .min(minima.seq2_overhang)
+            .min(params.bonus[1])
+            .min(0.);
+        let fixed = dangling_ends + MIN_EXTENSION;
+
+        let mut nn_stack = minima.nn_stack;
+        for (stack_min, penalty) in nn_stack.iter_mut().zip(minima.nn_penalty) {
+            if penalty {
+                *stack_min = stack_min.min(params.bonus[0]);
+            }
+        }
+        BoundTerms {
+            dangling_ends,
+            fixed,
+            nn_stack,
+        }
+    }
+}
+
+fn offset_lower_bound(
+    mask: u128,
+    first: usize,
+    end: usize,
+    terms: &BoundTerms,
+    params: &ScoringParams,
+) -> f64 {
+    // A bound that calc_at_offset can't score below, from the match mask of seq1[first..end]
+    // alone. The match bonuses are exact, the rest use the lowest possible scores.
+    // INFINITY if seq1 can't extend
+    if end < first + 2 || mask >> (end - 2) == 0 {
+        return f64::INFINITY;
+    }
+    let n = end - first;
+    let mut bound = terms.fixed;
+
+    // Match proportion is exact
+    bound += calc_match_prop_bonus(mask.count_ones() as usize, n, params);
+    // Longest match, as the number of shifts for every run to vanish
+    let mut longest_match = 0;
+    let mut runs = mask;
+    while runs != 0 {
+        runs &= runs >> 1;
+        longest_match += 1;
+    }
+    bound += -(longest_match as f64 * params.bonus[7]);
+    // Bubbles, as a run of k > 2 mismatches has k - 2 positions starting three mismatches
+    let mismatches = !mask & range_mask(first, end);
+    let bubbles = (mismatches & mismatches >> 1 & mismatches >> 2).count_ones();
+    bound += -((bubbles as f64) * params.bonus[0]) * params.bonus[9];
+
+    // NN stacks, counted by how many of their two positions mismatch
+    let stacks = range_mask(first, end - 1);
+    let both_match = (mask & mask >> 1 & stacks).count_ones() as usize;
+    let both_mismatch = (!mask & !(mask >> 1) & stacks).count_ones() as usize;
+    let counts = [
+        both_match,
+        n - 1 - both_match - both_mismatch,
+        both_mismatch,
+    ];
+    for (count, stack_min) in counts.iter().zip(terms.nn_stack) {
+        if *count > 0 {
+            bound += *count as f64 * stack_min;
+        }
+    }
+
+    // Allow for rounding, as the terms are summed in a different order
+    bound - 1e-9
+}
+
+fn calc_at_offset_packed(
+    seq1: &[usize],
+    seq2: &[usize],
+    mask: u128,
+    offset: i32,
+    table: &NnTable,
+    params: &ScoringParams,
+) -> Option<f64> {
+    // Identical to calc_at_offset_table, with the match bool from mask. The offset must be in range
+    let first = (-offset).max(0) as usize;
+    let end = seq1.len();
+    let seq2_index = |x: usize| (x as i32 + offset) as usize;
+
+    let mut dg_score = calc_dangling_ends_stabilty(
+        seq1,
+        seq2,
+        (seq2_index(first), first),
+        (seq2_index(end - 1), end - 1),
+        params,
+    );
 
-    match calc_extention(seq1, &match_bool) {
+    // calc_extention only looks at the final four positions
+    let tail_len = (end - first).min(4);
+    let mut tail = [false; 4];
+    for (i, is_match) in tail[..tail_len].iter_mut().enumerate() {
+        *is_match = mask >> (end - tail_len + i) & 1 == 1;
+    }
+    match calc_extention(seq1, &tail[..tail_len]) {
         Some(score) => dg_score += score,
         None => return None,
     };
 
-    // Apply longest match, and match proportion
-    dg_score += apply_bonus(&match_bool);
+    dg_score += sum_bonus(
+        mask.count_ones() as usize,
+        end - first,
+        MatchRuns::new(mask, first, end),
+        params,
+    );
+
+    // The 3' position has no NN stack
+    let stacks = (first..end - 1).map(|x| (seq2_index(x), x));
+    dg_score += calc_nn_thermo(table, seq1, seq2, stacks, params);
+
+    Some(dg_score)
+}
+
+fn extension_offsets(seq1: &[usize], seq2: &[usize]) -> Range<i32> {
+    // Every offset where seq1's 3' end could extend on seq2
+    if seq1.len() < 2 {
+        return 0..0;
+    }
+    -(seq1.len() as i32 - 2)..(seq2.len() as i32) - (seq1.len() as i32)
+}
+
+pub fn does_seq1_extend_acids(
+    seq1: &[usize],
+    seq2: &[usize],
+    acids: (NucleicAcid, NucleicAcid),
+    t: f64,
+    params: &ScoringParams,
+) -> bool {
+    // does_seq1_extend with the NN stacks of seq1 (acids.0) on seq2 (acids.1)
+    let table = nn_table(acids);
+    let mut seq2_rev = seq2.to_owned();
+    seq2_rev.reverse();
+
+    match (PackedSeq::new(seq1), PackedSeq::new(&seq2_rev)) {
+        (Some(packed1), Some(packed2)) => {
+            let terms = BoundTerms::new(acids, params);
+            extension_offsets(seq1, seq2).any(|offset| {
+                let mask = match_mask(&packed1, &packed2, offset);
+                // Skip offsets that can't score <= t
+                let first = (-offset).max(0) as usize;
+                offset_lower_bound(mask, first, seq1.len(), &terms, params) <= t
+                    && matches!(
+                        calc_at_offset_packed(seq1, &seq2_rev, mask, offset, table, params),
+                        Some(score) if score <= t
+                    )
+            })
+        }
+        // Too long to pack
+        _ => extension_offsets(seq1, seq2).any(|offset| {
+            matches!(
+                calc_at_offset_table(seq1, &seq2_rev, offset, table, params),
+                Some(score) if score <= t
+            )
+        }),
+    }
+}
 
-    // Remove the end element of mapping before giving to NN
-    mapping.pop();
-    dg_score += calc_nn_thermo(seq1, seq2, &mapping);
+fn seq1_min_score_below(
+    seq1: &[usize],
+    seq2_rev: &[usize],
+    packed: Option<(&PackedSeq, &PackedSeq)>,
+    t: f64,
+    table: &NnTable,
+    terms: &BoundTerms,
+    params: &ScoringParams,
+) -> Option<f64> {
+    // The lowest score of seq1 extending on seq2, if it is <= t. Offsets whose
+    // bound is above t are skipped, as in does_seq1_extend. terms must be for table
+    let scores = extension_offsets(seq1, seq2_rev).filter_map(|offset| match packed {
+        Some((packed1, packed2)) => {
+            let mask = match_mask(packed1, packed2, offset);
+            let first = (-offset).max(0) as usize;
+            match offset_lower_bound(mask, first, seq1.len(), terms, params) <= t {
+                true => calc_at_offset_packed(seq1, seq2_rev, mask, offset, table, params),
+                false => None,
+            }
+        }
+        None => calc_at_offset_table(seq1, seq2_rev, offset, table, params),
+    });
+    scores
+        .filter(|score| *score <= t)
+        .min_by(|a, b| a.total_cmp(b))
+}
+
+pub fn calc_seqs_min_score_below(
+    seq1: &[usize],
+    seq2: &[usize],
+    acids: (NucleicAcid, NucleicAcid),
+    extendable: (bool, bool),
+    t: f64,
+    params: &ScoringParams,
+) -> Option<f64> {
+    // calc_seqs_min_score_acids if it is <= t, otherwise None.
+    // Cheaper, as offsets that can't score <= t are skipped
+    let best_score = |extendable: bool, seq1: &[usize], seq2: &[usize], acids| {
+        if !extendable {
+            return None;
+        }
+        let seq2_rev: Vec<usize> = seq2.iter().rev().copied().collect();
+        let packed = PackedSeq::new(seq1).zip(PackedSeq::new(&seq2_rev));
+        let packed = packed.as_ref().map(|(packed1, packed2)| (packed1, packed2));
+        let terms = BoundTerms::new(acids, params);
+        seq1_min_score_below(seq1, &seq2_rev, packed, t, nn_table(acids), &terms, params)
+    };
+    let s1_score = best_score(extendable.0, seq1, seq2, acids);
+    let s2_score = best_score(extendable.1, seq2, seq1, (acids.1, acids.0));
 
-    return Some(dg_score);
+    match (s1_score, s2_score) {
+        (Some(s1), Some(s2)) => Some(s1.min(s2)),
+        (s1, s2) => s1.or(s2),
+    }
 }
 
-pub fn does_seq1_extend(seq1: &[usize], seq2: &[usize], t: f64) -> bool {
+pub fn seq1_best_offset_acids(
+    seq1: &[usize],
+    seq2: &[usize],
+    acids: (NucleicAcid, NucleicAcid),
+    params: &ScoringParams,
+) -> Option<(i32, f64)> {
+    // Returns the (offset, score) with the lowest score, or None if seq1 never extends.
+    // The NN stacks are those of seq1 (acids.0) on seq2 (acids.1)
+    let table = nn_table(acids);
     let mut seq2_rev = seq2.to_owned();
     seq2_rev.reverse();
+    let packed = (PackedSeq::new(seq1), PackedSeq::new(&seq2_rev));
 
-    for offset in -(seq1.len() as i32 - 2)..(seq2.len() as i32) - (seq1.len() as i32) {
-        match calc_at_offset(&seq1, &seq2_rev, offset) {
-            Some(score) => {
-                //println!("{}", score);
-                if score <= t {
-                    return true;
-                }
+    let mut best: Option<(i32, f64)> = None;
+    for offset in extension_offsets(seq1, seq2) {
+        let score = match packed {
+            (Some(packed1), Some(packed2)) => {
+                let mask = match_mask(&packed1, &packed2, offset);
+                calc_at_offset_packed(seq1, &seq2_rev, mask, offset, table, params)
+            }
+            _ => calc_at_offset_table(seq1, &seq2_rev, offset, table, params),
+        };
+        if let Some(score) = score {
+            match best {
+                Some((_, best_score)) if best_score <= score => (),
+                _ => best = Some((offset, score)),
             }
-            None => (),
         }
     }
-    return false;
+    best
+}
+
+pub fn calc_seqs_min_score_acids(
+    seq1: &[usize],
+    seq2: &[usize],
+    acids: (NucleicAcid, NucleicAcid),
+    extendable: (bool, bool),
+    params: &ScoringParams,
+) -> Option<f64> {
+    // The lowest score of either seq extending on the other, with the stacks from nn_table.
+    // Only seqs with an extendable 3' end are scored extending. A 3' blocked seq can still be
+    // the template
+    let best_score = |extendable: bool, seq1: &[usize], seq2: &[usize], acids| match extendable {
+        true => seq1_best_offset_acids(seq1, seq2, acids, params).map(|(_, score)| score),
+        false => None,
+    };
+    let s1_score = best_score(extendable.0, seq1, seq2, acids);
+    let s2_score = best_score(extendable.1, seq2, seq1, (acids.1, acids.0));
+
+    match (s1_score, s2_score) {
+        (Some(s1), Some(s2)) => Some(s1.min(s2)),
+        (s1, s2) => s1.or(s2),
+    }
+}
+
+/// The seqs of a kmer, and if their 3' ends can extend
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KmerSeqs<'a> {
+    pub seqs: &'a [Vec<usize>],
+    // False for a 3' blocked kmer, which can only be the template
+    pub extendable: bool,
+    pub acid: NucleicAcid,
+    // The number of 5' bases of each seq that are an adapter tail
+    pub tail_len: usize,
+}
+
+impl<'a> KmerSeqs<'a> {
+    pub fn new(seqs: &'a [Vec<usize>]) -> KmerSeqs<'a> {
+        KmerSeqs {
+            seqs,
+            extendable: true,
+            acid: NucleicAcid::Dna,
+            tail_len: 0,
+        }
+    }
+}
+
+pub fn calc_kmers_min_score(
+    kmer1: &KmerSeqs,
+    kmer2: &KmerSeqs,
+    policy: &TailPolicy,
+    params: &ScoringParams,
+) -> Option<f64> {
+    // The lowest score between any seq in kmer1 and any in kmer2, with their tails
+    // weighted by policy
+    kmer1
+        .seqs
+        .iter()
+        .cartesian_product(kmer2.seqs.iter())
+        .filter_map(|(s1, s2)| calc_kmer_seqs_min_score(kmer1, s1, kmer2, s2, policy, params))
+        .min_by(|a, b| a.total_cmp(b))
+}
+
+fn calc_kmer_seqs_min_score(
+    kmer1: &KmerSeqs,
+    seq1: &[usize],
+    kmer2: &KmerSeqs,
+    seq2: &[usize],
+    policy: &TailPolicy,
+    params: &ScoringParams,
+) -> Option<f64> {
+    // calc_seqs_min_score_tailed of a seq from each kmer
+    let tails = (kmer1.tail_len, kmer2.tail_len);
+    let acids = (kmer1.acid, kmer2.acid);
+    let extendable = (kmer1.extendable, kmer2.extendable);
+    calc_seqs_min_score_tailed(seq1, seq2, tails, acids, extendable, policy, params)
+}
+
+pub fn calc_kmers_min_score_below(
+    kmer1: &KmerSeqs,
+    kmer2: &KmerSeqs,
+    t: f64,
+    policy: &TailPolicy,
+    params: &ScoringParams,
+) -> Option<f64> {
+    // calc_kmers_min_score if it is <= t, otherwise None.
+    // Only seqs scored as untailed skip the offsets that can't score <= t
+    if !policy.is_unweighted((kmer1.tail_len, kmer2.tail_len)) {
+        return calc_kmers_min_score(kmer1, kmer2, policy, params).filter(|s| *s <= t);
+    }
+    let extendable = (kmer1.extendable, kmer2.extendable);
+    let acids = (kmer1.acid, kmer2.acid);
+    kmer1
+        .seqs
+        .iter()
+        .cartesian_product(kmer2.seqs.iter())
+        .filter_map(|(s1, s2)| calc_seqs_min_score_below(s1, s2, acids, extendable, t, params))
+        .min_by(|a, b| a.total_cmp(b))
 }
 
-pub fn do_seqs_interact(seq1: &str, seq2: &str, t: f64) -> bool {
+pub fn do_kmers_interact(
+    kmer1: &KmerSeqs,
+    kmer2: &KmerSeqs,
+    t: f64,
+    policy: &TailPolicy,
+    params: &ScoringParams,
+) -> bool {
+    // If any seq in kmer1 and any in kmer2 score <= t, in the directions that can extend
+    if !policy.is_unweighted((kmer1.tail_len, kmer2.tail_len)) {
+        return calc_kmers_min_score_below(kmer1, kmer2, t, policy, params).is_some();
+    }
+    let acids = (kmer1.acid, kmer2.acid);
+    kmer1
+        .seqs
+        .iter()
+        .cartesian_product(kmer2.seqs.iter())
+        .any(|(s1, s2)| {
+            (kmer1.extendable && does_seq1_extend_acids(s1, s2, acids, t, params))
+                || (kmer2.extendable
+                    && does_seq1_extend_acids(s2, s1, (acids.1, acids.0), t, params))
+        })
+}
+
+fn do_acid_seqs_interact(
+    seq1: &[usize],
+    seq2: &[usize],
+    acids: (NucleicAcid, NucleicAcid),
+    t: f64,
+    params: &ScoringParams,
+) -> bool {
+    does_seq1_extend_acids(seq1, seq2, acids, t, params)
+        | does_seq1_extend_acids(seq2, seq1, (acids.1, acids.0), t, params)
+}
+
+pub fn do_seqs_interact(seq1: &str, seq2: &str, t: f64, params: &ScoringParams) -> bool {
+    // Seqs containing U are scored as RNA
     let s1 = encode_base(seq1);
     let s2 = encode_base(seq2);
+    let acids = (NucleicAcid::of(seq1), NucleicAcid::of(seq2));
 
-    return does_seq1_extend(&s1, &s2, t) | does_seq1_extend(&s2, &s1, t);
+    do_acid_seqs_interact(&s1, &s2, acids, t, params)
 }
 
-pub fn do_pools_interact(pool1: Vec<&str>, pool2: Vec<&str>, t: f64) -> bool {
+pub fn do_pools_interact(
+    pool1: Vec<&str>,
+    pool2: Vec<&str>,
+    t: f64,
+    params: &ScoringParams,
+) -> bool {
     // Encode the pools
-    let pool1_encoded: Vec<Vec<usize>> = pool1.iter().map(|s| encode_base(s)).collect();
-    let pool2_encoded: Vec<Vec<usize>> = pool2.iter().map(|s| encode_base(s)).collect();
+    let encode = |pool: Vec<&str>| -> Vec<(Vec<usize>, NucleicAcid)> {
+        pool.iter()
+            .map(|s| (encode_base(s), NucleicAcid::of(s)))
+            .collect()
+    };
+    let pool1_encoded = encode(pool1);
+    let pool2_encoded = encode(pool2);
 
     // Will look for interactions between every seq in pool1 and pool2
-    for (s1, s2) in pool1_encoded.iter().cartesian_product(pool2_encoded.iter()) {
-        if does_seq1_extend(&s1, &s2, t) | does_seq1_extend(&s2, &s1, t) {
+    for ((s1, a1), (s2, a2)) in pool1_encoded.iter().cartesian_product(pool2_encoded.iter()) {
+        if do_acid_seqs_interact(s1, s2, (*a1, *a2), t, params) {
             return true;
         }
     }
-    return false;
+    false
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
+
+    fn dna_min_score(seq1: &[usize], seq2: &[usize]) -> Option<f64> {
+        // The lowest score of either DNA seq extending on the other
+        calc_seqs_min_score_acids(seq1, seq2, DNA_PAIR, (true, true), &DEFAULT_PARAMS)
+    }
     #[test]
     fn test_valid_encode_base() {
         let seq = "ATCG";
@@ -312,7 +1054,13 @@ mod tests {
 
         // base_to_encode = {"A": 0, "T": 3, "C": 1, "G": 2}
         assert_eq!(
-            calc_nn_thermo(&encode_base(seq1), &encode_base(seq2), &mapping),
+            calc_nn_thermo(
+                &NN_SCORES,
+                &encode_base(seq1),
+                &encode_base(seq2),
+                mapping.into_iter(),
+                &DEFAULT_PARAMS
+            ),
             pred_score
         )
     }
@@ -354,7 +1102,13 @@ mod tests {
 
         // base_to_encode = {"A": 0, "T": 1, "C": 2, "G": 3}
         assert_eq!(
-            calc_nn_thermo(&encode_base(seq1), &encode_base(seq2), &mapping),
+            calc_nn_thermo(
+                &NN_SCORES,
+                &encode_base(seq1),
+                &encode_base(seq2),
+                mapping.into_iter(),
+                &DEFAULT_PARAMS
+            ),
             pred_score
         )
     }
@@ -376,32 +1130,32 @@ mod tests {
         assert!(super::MATCH_ARRAY[g][c]);
         // MISMATCHES
         // A / A
-        assert_eq!(super::MATCH_ARRAY[a][a], false);
+        assert!(!super::MATCH_ARRAY[a][a]);
         // A / C
-        assert_eq!(super::MATCH_ARRAY[a][c], false);
+        assert!(!super::MATCH_ARRAY[a][c]);
         // A / G
-        assert_eq!(super::MATCH_ARRAY[a][g], false);
+        assert!(!super::MATCH_ARRAY[a][g]);
 
         // T / T
-        assert_eq!(super::MATCH_ARRAY[t][t], false);
+        assert!(!super::MATCH_ARRAY[t][t]);
         // T / C
-        assert_eq!(super::MATCH_ARRAY[t][c], false);
+        assert!(!super::MATCH_ARRAY[t][c]);
         // T / G
-        assert_eq!(super::MATCH_ARRAY[t][g], false);
+        assert!(!super::MATCH_ARRAY[t][g]);
 
         // C / C
-        assert_eq!(super::MATCH_ARRAY[c][c], false);
+        assert!(!super::MATCH_ARRAY[c][c]);
         // C / A
-        assert_eq!(super::MATCH_ARRAY[c][a], false);
+        assert!(!super::MATCH_ARRAY[c][a]);
         // C / T
-        assert_eq!(super::MATCH_ARRAY[c][t], false);
+        assert!(!super::MATCH_ARRAY[c][t]);
 
         // G / G
-        assert_eq!(super::MATCH_ARRAY[g][g], false);
+        assert!(!super::MATCH_ARRAY[g][g]);
         // G / A
-        assert_eq!(super::MATCH_ARRAY[g][a], false);
+        assert!(!super::MATCH_ARRAY[g][a]);
         // G / T
-        assert_eq!(super::MATCH_ARRAY[g][t], false);
+        assert!(!super::MATCH_ARRAY[g][t]);
     }
     #[test]
     fn test_ensure_consistant_result() {
@@ -416,7 +1170,13 @@ mod tests {
         let offset = -12;
 
         assert_eq!(
-            super::calc_at_offset(&encode_base(s1), &encode_base(s2), offset),
+            super::calc_at_offset_table(
+                &encode_base(s1),
+                &encode_base(s2),
+                offset,
+                &NN_SCORES,
+                &DEFAULT_PARAMS
+            ),
             Some(-40.736826004)
         );
     }
@@ -432,16 +1192,263 @@ mod tests {
         let s2 = "TGGAAATACCCACAAGTTAATGGTTTAAC"; //5'-3'
         let threshold = -27.0;
 
-        assert!(super::does_seq1_extend(
+        assert!(super::does_seq1_extend_acids(
             &encode_base(s1),
             &encode_base(s2),
+            DNA_PAIR,
             threshold,
+            &DEFAULT_PARAMS,
         ));
     }
     #[test]
+    fn test_breakdown_matches_score() {
+        let s1 = encode_base("ACACCTGTGCCTGTTAAACCAT");
+        let s2 = encode_base("CAATTTGGTAATTGAACACCCATAAAGGT");
+        let offset = -12;
+
+        let breakdown =
+            super::calc_at_offset_breakdown(&s1, &s2, offset, DNA_PAIR, true, &DEFAULT_PARAMS)
+                .unwrap();
+        assert_eq!(
+            Some(breakdown.score),
+            super::calc_at_offset_table(&s1, &s2, offset, &NN_SCORES, &DEFAULT_PARAMS)
+        );
+
+        // The terms should sum to the score
+        let sum = breakdown.dangling_ends
+            + breakdown.extension
+            + breakdown.match_prop
+            + breakdown.longest_match
+            + breakdown.bubbles
+            + breakdown.nn_stack;
+        assert!((sum - breakdown.score).abs() < 1e-9);
+
+        // Only seq1 bases 12.. overlap seq2
+        let positions = breakdown.positions.unwrap();
+        assert_eq!(positions.len(), 10);
+        assert_eq!(positions[0].seq1_index, 12);
+        assert_eq!(positions[0].seq2_index, 0);
+        assert_eq!(positions[9].nn_score, None);
+        assert!(positions.iter().all(|p| p.is_match));
+    }
+    #[test]
+    fn test_breakdown_no_extension() {
+        // 3' bases do not match so there is no score
+        let s1 = encode_base("AAAAAA");
+        let s2 = encode_base("AAAAAAAA");
+        assert_eq!(
+            super::calc_at_offset_breakdown(&s1, &s2, 0, DNA_PAIR, false, &DEFAULT_PARAMS),
+            None
+        );
+    }
+    #[test]
+    fn test_best_offset() {
+        // nCoV-2019_76_RIGHT_0 nCoV-2019_18_LEFT_0
+        let s1 = encode_base("ACACCTGTGCCTGTTAAACCAT"); //5'-3'
+        let s2 = encode_base("TGGAAATACCCACAAGTTAATGGTTTAAC"); //5'-3'
+
+        let (offset, score) =
+            super::seq1_best_offset_acids(&s1, &s2, DNA_PAIR, &DEFAULT_PARAMS).unwrap();
+        assert_eq!(offset, -12);
+        assert_eq!(score, -40.736826004);
+        assert!(dna_min_score(&s1, &s2).unwrap() <= score);
+    }
+    #[test]
     fn test_encode_decode() {
         // Test round trip encoding and decoding
         let seq = "CTCTTGTAGATCTGTTCTCTAAACGAACTTT";
         assert_eq!(decode_base(&encode_base(seq)), seq);
     }
+    #[test]
+    fn test_blocked_3p() {
+        // seq1's 3' end binds the 5' end of seq2, but seq2 can't extend on seq1.
+        // A 3' blocked kmer can't extend, but can still be the template
+        let seqs1 = [encode_base("ACACTCTTTCCCTACACGACTTGCAGTGTCGTGTAGG")];
+        let seqs2 = [encode_base("ACACTCTTTCCCTACACGACCCTTAGCGAAGATCCA")];
+        let best_score = |s1, s2| {
+            seq1_best_offset_acids(s1, s2, DNA_PAIR, &DEFAULT_PARAMS).map(|(_, score)| score)
+        };
+        let s1_score = best_score(&seqs1[0], &seqs2[0]);
+        let s2_score = best_score(&seqs2[0], &seqs1[0]);
+        assert!(s1_score.unwrap() <= -26.);
+        assert!(!matches!(s2_score, Some(s) if s <= -26.));
+
+        let (mut kmer1, mut kmer2) = (KmerSeqs::new(&seqs1), KmerSeqs::new(&seqs2));
+        let policy = TailPolicy::default();
+        for (extendable, expected) in [
+            ((true, true), s1_score),
+            ((true, false), s1_score),
+            ((false, true), s2_score),
+            ((false, false), None),
+        ] {
+            (kmer1.extendable, kmer2.extendable) = extendable;
+            assert_eq!(
+                calc_kmers_min_score(&kmer1, &kmer2, &policy, &DEFAULT_PARAMS),
+                expected
+            );
+            assert_eq!(
+                calc_seqs_min_score_acids(
+                    &seqs1[0],
+                    &seqs2[0],
+                    DNA_PAIR,
+                    extendable,
+                    &DEFAULT_PARAMS
+                ),
+                expected
+            );
+        }
+    }
+    #[test]
+    fn test_short_seqs() {
+        // Seqs of 0 or 1 bases never interact
+        for seq in ["", "A"] {
+            let s = encode_base(seq);
+            let other = encode_base("ATATATATAT");
+            assert_eq!(dna_min_score(&s, &other), None);
+            assert_eq!(dna_min_score(&s, &s), None);
+            assert!(!do_seqs_interact(seq, "ATATATATAT", 0., &DEFAULT_PARAMS));
+        }
+        // Two bases can extend, but only onto a longer seq with a base past their 3' end
+        let s = encode_base("AT");
+        assert!(dna_min_score(&s, &s).is_none());
+        assert!(dna_min_score(&s, &encode_base("ATATATATAT")).is_some());
+    }
+    #[test]
+    fn test_out_of_range_offset() {
+        // seq1's 3' end overhangs seq2, so it can't extend
+        let s1 = encode_base("ACGT");
+        let s2 = encode_base("ACGT");
+        assert_eq!(
+            calc_at_offset_table(&s1, &s2, 0, &NN_SCORES, &DEFAULT_PARAMS),
+            None
+        );
+        assert_eq!(
+            calc_at_offset_table(&s1, &s2, -3, &NN_SCORES, &DEFAULT_PARAMS),
+            None
+        );
+        assert_eq!(
+            calc_at_offset_table(&s1, &s2, i32::MIN, &NN_SCORES, &DEFAULT_PARAMS),
+            None
+        );
+        assert_eq!(
+            calc_at_offset_table(&s1, &s2, i32::MAX, &NN_SCORES, &DEFAULT_PARAMS),
+            None
+        );
+    }
+    #[test]
+    fn test_invalid_base() {
+        let s1 = vec![0, 1, 7, 3];
+        let s2 = encode_base("ACGTACGT");
+        assert_eq!(
+            try_calc_at_offset(
+                &s1,
+                &s2,
+                0,
+                DNA_PAIR,
+                (0, 0),
+                &TailPolicy::default(),
+                &DEFAULT_PARAMS
+            ),
+            Err(ScoreError::InvalidBase {
+                seq: 1,
+                index: 2,
+                base: 7
+            })
+        );
+    }
+
+    const DNA_PAIR: (NucleicAcid, NucleicAcid) = (NucleicAcid::Dna, NucleicAcid::Dna);
+    fn acid_pair(rna: (bool, bool)) -> (NucleicAcid, NucleicAcid) {
+        let acid = |rna| match rna {
+            true => NucleicAcid::Rna,
+            false => NucleicAcid::Dna,
+        };
+        (acid(rna.0), acid(rna.1))
+    }
+    proptest! {
+        #[test]
+        fn prop_calc_at_offset_never_panics(
+            seq1 in prop::collection::vec(0..4usize, 0..40),
+            seq2 in prop::collection::vec(0..4usize, 0..40),
+            offset in -60..60i32,
+        ) {
+            let score = calc_at_offset_table(&seq1, &seq2, offset, &NN_SCORES, &DEFAULT_PARAMS);
+            if !offset_in_range(&seq1, &seq2, offset) {
+                prop_assert_eq!(score, None);
+            }
+            // The breakdown scores the same
+            let breakdown = calc_at_offset_breakdown(&seq1, &seq2, offset, DNA_PAIR, true, &DEFAULT_PARAMS);
+            prop_assert_eq!(score, breakdown.map(|b| b.score));
+        }
+        #[test]
+        fn prop_try_calc_at_offset_never_panics(
+            seq1 in prop::collection::vec(0..6usize, 0..12),
+            seq2 in prop::collection::vec(0..6usize, 0..12),
+            offset in any::<i32>(),
+        ) {
+            let result = try_calc_at_offset(&seq1, &seq2, offset, DNA_PAIR, (0, 0), &TailPolicy::default(), &DEFAULT_PARAMS);
+            let valid = seq1.iter().chain(seq2.iter()).all(|b| *b < 4);
+            prop_assert_eq!(result.is_ok(), valid);
+        }
+        #[test]
+        fn prop_offset_lower_bound(
+            seq1 in prop::collection::vec(0..4usize, 2..40),
+            seq2 in prop::collection::vec(0..4usize, 3..40),
+            offset_index in 0..1000usize,
+            rna in any::<(bool, bool)>(),
+        ) {
+            let acids = acid_pair(rna);
+            let table = nn_table(acids);
+            let offsets = extension_offsets(&seq1, &seq2);
+            let offset = offsets.start + (offset_index % offsets.len()) as i32;
+            let mask = match_mask(&PackedSeq::new(&seq1).unwrap(), &PackedSeq::new(&seq2).unwrap(), offset);
+            let first = (-offset).max(0) as usize;
+            let terms = BoundTerms::new(acids, &DEFAULT_PARAMS);
+            let bound = offset_lower_bound(mask, first, seq1.len(), &terms, &DEFAULT_PARAMS);
+            let score = calc_at_offset_table(&seq1, &seq2, offset, table, &DEFAULT_PARAMS);
+            prop_assert!(!matches!(score, Some(s) if s < bound));
+            // The packed score is identical, not just close
+            let packed = calc_at_offset_packed(&seq1, &seq2, mask, offset, table, &DEFAULT_PARAMS);
+            prop_assert_eq!(packed.map(f64::to_bits), score.map(f64::to_bits));
+        }
+        #[test]
+        fn prop_pruning_unchanged(
+            seq1 in prop::collection::vec(0..4usize, 0..30),
+            seq2 in prop::collection::vec(0..4usize, 0..30),
+            t in -40.0..0.0f64,
+            rna in any::<(bool, bool)>(),
+        ) {
+            // does_seq1_extend_acids agrees with the unpruned scan
+            let acids = acid_pair(rna);
+            let table = nn_table(acids);
+            let mut seq2_rev = seq2.clone();
+            seq2_rev.reverse();
+            let unpruned = extension_offsets(&seq1, &seq2).any(|o| {
+                matches!(calc_at_offset_table(&seq1, &seq2_rev, o, table, &DEFAULT_PARAMS), Some(s) if s <= t)
+            });
+            prop_assert_eq!(does_seq1_extend_acids(&seq1, &seq2, acids, t, &DEFAULT_PARAMS), unpruned);
+        }
+        #[test]
+        fn prop_min_score_below_unchanged(
+            seq1 in prop::collection::vec(0..4usize, 0..30),
+            seq2 in prop::collection::vec(0..4usize, 0..30),
+            extendable in any::<(bool, bool)>(),
+            t in -40.0..0.0f64,
+            rna in any::<(bool, bool)>(),
+        ) {
+            // The pruned min score is the unpruned one, when it is <= t
+            let acids = acid_pair(rna);
+            let unpruned = calc_seqs_min_score_acids(&seq1, &seq2, acids, extendable, &DEFAULT_PARAMS)
+                .filter(|s| *s <= t);
+            let pruned = calc_seqs_min_score_below(&seq1, &seq2, acids, extendable, t, &DEFAULT_PARAMS);
+            prop_assert_eq!(pruned.map(f64::to_bits), unpruned.map(f64::to_bits));
+        }
+        #[test]
+        fn prop_calc_seqs_min_score_never_panics(
+            seq1 in prop::collection::vec(0..4usize, 0..8),
+            seq2 in prop::collection::vec(0..4usize, 0..8),
+        ) {
+            dna_min_score(&seq1, &seq2);
+        }
+    }
 }
@@ -0,0 +1,165 @@
+use std::fmt;
+use std::str::FromStr;
+
+pub const BONUS_ARRAY: [f64; 10] = [
+    1.11217618,
+    0.55187469,
+    1.01582516,
+    1.03180592,
+    -2.76687727,
+    -0.81903133,
+    0.93596145,
+    2.32758405,
+    3.24507248,
+    0.80416919,
+];
+// 0 = PENALTY_DOUBLE_MISMATCH
+// 1 = PENALTY_LEFT_OVERHANG_MISMATCH
+// 2 = PENALTY_RIGHT_OVERHANG_MISMATCH
+// 3 = BONUS_ALL_MATCH
+// 4 = BONUS_3P_MATCH_GC
+// 5 = BONUS_3P_MATCH_AT
+// 6 = SCORE_3P_MISMATCH
+// 7 = LONGEST_MATCH_COEF
+// 8 = MATCH_PROP_COEF
+// 9 = BUBBLE_COEF
+pub static BONUS_NAMES: [&str; 10] = [
+    "PENALTY_DOUBLE_MISMATCH",
+    "PENALTY_LEFT_OVERHANG_MISMATCH",
+    "PENALTY_RIGHT_OVERHANG_MISMATCH",
+    "BONUS_ALL_MATCH",
+    "BONUS_3P_MATCH_GC",
+    "BONUS_3P_MATCH_AT",
+    "SCORE_3P_MISMATCH",
+    "LONGEST_MATCH_COEF",
+    "MATCH_PROP_COEF",
+    "BUBBLE_COEF",
+];
+// Written only if the params have a logistic
+pub static LOGISTIC_NAMES: [&str; 2] = ["LOGISTIC_INTERCEPT", "LOGISTIC_SLOPE"];
+
+/// The coefficients used by the scoring function
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoringParams {
+    pub bonus: [f64; 10],
+    // The intercept and slope from score to dimer probability. None unless fitted,
+    // as the packaged coefficients have no calibrated probability
+    pub logistic: Option<[f64; 2]>,
+}
+
+pub static DEFAULT_PARAMS: ScoringParams = ScoringParams {
+    bonus: BONUS_ARRAY,
+    logistic: None,
+};
+
+impl Default for ScoringParams {
+    fn default() -> Self {
+        DEFAULT_PARAMS
+    }
+}
+
+impl fmt::Display for ScoringParams {
+    // Written as one `NAME<tab>VALUE` line per coefficient
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, value) in BONUS_NAMES.iter().zip(self.bonus.iter()) {
+            writeln!(f, "{}\t{}", name, value)?;
+        }
+        if let Some(logistic) = self.logistic {
+            for (name, value) in LOGISTIC_NAMES.iter().zip(logistic.iter()) {
+                writeln!(f, "{}\t{}", name, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ScoringParams {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut bonus: [Option<f64>; 10] = [None; 10];
+        let mut logistic: [Option<f64>; 2] = [None; 2];
+
+        for line in s.lines() {
+            let line = line.trim();
+            // Skip blank lines and comments
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, value) = match line.split_once(char::is_whitespace) {
+                Some((name, value)) => (name, value.trim()),
+                None => return Err(format!("Invalid line in scoring params: {}", line)),
+            };
+            let slot = match BONUS_NAMES.iter().position(|n| *n == name) {
+                Some(index) => &mut bonus[index],
+                None => match LOGISTIC_NAMES.iter().position(|n| *n == name) {
+                    Some(index) => &mut logistic[index],
+                    None => return Err(format!("Unknown scoring param: {}", name)),
+                },
+            };
+            match value.parse::<f64>() {
+                Ok(v) => *slot = Some(v),
+                Err(_) => return Err(format!("Invalid value for {}: {}", name, value)),
+            }
+        }
+
+        let mut params = ScoringParams {
+            bonus: [0.; 10],
+            logistic: None,
+        };
+        for (index, value) in bonus.iter().enumerate() {
+            match value {
+                Some(v) => params.bonus[index] = *v,
+                None => return Err(format!("Missing scoring param: {}", BONUS_NAMES[index])),
+            }
+        }
+        // The logistic is optional, but needs both its coefficients
+        params.logistic = match logistic {
+            [Some(intercept), Some(slope)] => Some([intercept, slope]),
+            [None, None] => None,
+            [Some(_), None] => return Err(format!("Missing scoring param: {}", LOGISTIC_NAMES[1])),
+            [None, Some(_)] => return Err(format!("Missing scoring param: {}", LOGISTIC_NAMES[0])),
+        };
+        Ok(params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_round_trip() {
+        let params = ScoringParams::default();
+        let parsed: ScoringParams = params.to_string().parse().unwrap();
+        assert_eq!(parsed, params);
+
+        // With a fitted logistic
+        let params = ScoringParams {
+            logistic: Some([-7.8, -0.3]),
+            ..params
+        };
+        let parsed: ScoringParams = params.to_string().parse().unwrap();
+        assert_eq!(parsed, params);
+    }
+    #[test]
+    fn test_half_logistic() {
+        let s = format!("{}LOGISTIC_INTERCEPT\t-7.8\n", ScoringParams::default());
+        assert_eq!(
+            s.parse::<ScoringParams>(),
+            Err("Missing scoring param: LOGISTIC_SLOPE".to_string())
+        );
+    }
+    #[test]
+    fn test_missing_param() {
+        let s = "# Only one param\nBUBBLE_COEF\t0.5\n";
+        assert_eq!(
+            s.parse::<ScoringParams>(),
+            Err("Missing scoring param: PENALTY_DOUBLE_MISMATCH".to_string())
+        );
+    }
+    #[test]
+    fn test_unknown_param() {
+        let s = format!("{}NOT_A_PARAM\t1.0\n", ScoringParams::default());
+        assert!(s.parse::<ScoringParams>().is_err());
+    }
+}
@@ -0,0 +1,154 @@
+use super::{seq1_best_offset_acids, NucleicAcid, ScoringParams, MATCH_ARRAY};
+
+/// The double stranded product once the 3' ends of a dimer extend
+#[derive(Debug, Clone, PartialEq)]
+pub struct DimerProduct {
+    // The offset and score of the extending sequence. See does_seq1_extend
+    pub offset: i32,
+    pub score: f64,
+    // Each strand 5'-3' after extension
+    pub seq1_strand: Vec<usize>,
+    pub seq2_strand: Vec<usize>,
+    pub seq1_extends: bool,
+    pub seq2_extends: bool,
+    // Length of the duplex including any overhang
+    pub length: usize,
+}
+
+fn complement(base: usize) -> usize {
+    // base_to_encode = {"A": 0, "T": 3, "C": 1, "G": 2}
+    3 - base
+}
+
+pub fn build_dimer_product(
+    seq1: &[usize],
+    seq2: &[usize],
+    offset: i32,
+    score: f64,
+) -> DimerProduct {
+    // Builds the product of seq1 extending on seq2 at this offset.
+    // seq1[x] pairs with seq2_rev[x + offset], so the 3' end of seq2 sits at x = -offset
+    let mut seq2_rev = seq2.to_owned();
+    seq2_rev.reverse();
+    let seq2_3p = -offset;
+
+    // seq1 copies the rest of seq2 past its own 3' end
+    let mut seq1_strand = seq1.to_owned();
+    for base in seq2_rev[(seq1.len() as i32 + offset) as usize..].iter() {
+        seq1_strand.push(complement(*base));
+    }
+
+    // seq2 can only extend if its 3' end lies within seq1, and is anchored in its final two bases
+    let seq2_extends = seq2_3p > 0
+        && (MATCH_ARRAY[seq1[seq2_3p as usize]][seq2_rev[0]]
+            || MATCH_ARRAY[seq1[seq2_3p as usize + 1]][seq2_rev[1]]);
+
+    let mut seq2_strand = seq2.to_owned();
+    if seq2_extends {
+        for base in seq1[..seq2_3p as usize].iter().rev() {
+            seq2_strand.push(complement(*base));
+        }
+    }
+
+    let left = seq2_3p.min(0);
+    let right = seq2.len() as i32 - offset;
+
+    DimerProduct {
+        offset,
+        score,
+        seq1_strand,
+        seq2_strand,
+        seq1_extends: true,
+        seq2_extends,
+        length: (right - left) as usize,
+    }
+}
+
+pub fn predict_dimer_product(
+    seq1: &[usize],
+    seq2: &[usize],
+    acids: (NucleicAcid, NucleicAcid),
+    params: &ScoringParams,
+) -> Option<DimerProduct> {
+    // Uses the lowest scoring offset of either sequence extending on the other
+    let s1_best = seq1_best_offset_acids(seq1, seq2, acids, params);
+    let s2_best = seq1_best_offset_acids(seq2, seq1, (acids.1, acids.0), params);
+
+    match (s1_best, s2_best) {
+        (Some((_, s1_score)), Some((offset, s2_score))) if s2_score < s1_score => {
+            Some(swap(build_dimer_product(seq2, seq1, offset, s2_score)))
+        }
+        (Some((offset, score)), _) => Some(build_dimer_product(seq1, seq2, offset, score)),
+        (None, Some((offset, score))) => Some(swap(build_dimer_product(seq2, seq1, offset, score))),
+        (None, None) => None,
+    }
+}
+
+fn swap(product: DimerProduct) -> DimerProduct {
+    // Swap the roles of seq1 and seq2 so they match the callers order
+    DimerProduct {
+        seq1_strand: product.seq2_strand,
+        seq2_strand: product.seq1_strand,
+        seq1_extends: product.seq2_extends,
+        seq2_extends: product.seq1_extends,
+        ..product
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{decode_base, encode_base, DEFAULT_PARAMS};
+    use super::*;
+    const DNA_PAIR: (NucleicAcid, NucleicAcid) = (NucleicAcid::Dna, NucleicAcid::Dna);
+    #[test]
+    fn test_both_extend() {
+        // nCoV-2019_76_RIGHT_0 nCoV-2019_18_LEFT_0
+        // 5'-ACACCTGTGCCTGTTAAACCAT-3' >
+        //                ||||||||||
+        //             3'-CAATTTGGTAATTGAACACCCATAAAGGT-5'
+        let s1 = encode_base("ACACCTGTGCCTGTTAAACCAT");
+        let s2 = encode_base("TGGAAATACCCACAAGTTAATGGTTTAAC");
+
+        let product = build_dimer_product(&s1, &s2, -12, -40.736826004);
+        assert!(product.seq1_extends && product.seq2_extends);
+        assert_eq!(product.length, 41);
+        assert_eq!(
+            decode_base(&product.seq1_strand),
+            "ACACCTGTGCCTGTTAAACCATTAACTTGTGGGTATTTCCA"
+        );
+
+        // Both strands are the full duplex
+        let rc: Vec<usize> = product.seq2_strand.iter().rev().map(|b| 3 - b).collect();
+        assert_eq!(rc, product.seq1_strand);
+
+        // seq2 extending on seq1 scores lower, but makes the same product
+        let best = predict_dimer_product(&s1, &s2, DNA_PAIR, &DEFAULT_PARAMS).unwrap();
+        assert!(best.score < product.score);
+        assert_eq!(best.seq1_strand, product.seq1_strand);
+        assert_eq!(best.seq2_strand, product.seq2_strand);
+    }
+    #[test]
+    fn test_seq2_overhang() {
+        //     5'-GGGACGT-3' >
+        //           ||||
+        //   3'-TTTTTTGCAAAA-5'
+        // The 3' end of seq2 overhangs the 5' end of seq1, so only seq1 extends
+        let s1 = encode_base("GGGACGT");
+        let s2 = encode_base("AAAACGTTTTTT");
+
+        let product = build_dimer_product(&s1, &s2, 2, -10.);
+        assert!(!product.seq2_extends);
+        assert_eq!(decode_base(&product.seq1_strand), "GGGACGTTTT");
+        assert_eq!(decode_base(&product.seq2_strand), "AAAACGTTTTTT");
+        assert_eq!(product.length, 12);
+    }
+    #[test]
+    fn test_no_product() {
+        let s1 = encode_base("AAAAAA");
+        let s2 = encode_base("AAAAAAAA");
+        assert_eq!(
+            predict_dimer_product(&s1, &s2, DNA_PAIR, &DEFAULT_PARAMS),
+            None
+        );
+    }
+}
@@ -0,0 +1,339 @@
+use std::fs;
+use std::io;
+
+use super::rna::{nn_table, NucleicAcid};
+use super::scores::NnTable;
+use super::{calc_at_offset_table, ScoringParams, Site, Strand, MATCH_ARRAY};
+
+// Encoding for any base that isn't ACGT, such as N or IUPAC codes
+pub const OTHER_BASE: usize = 4;
+
+pub struct FastaRecord {
+    pub name: String,
+    // Encoded with encode_base, with OTHER_BASE for non ACGT bases
+    pub seq: Vec<usize>,
+}
+
+pub fn encode_reference(sequence: &str) -> Vec<usize> {
+    // Like encode_base, but tolerant of non ACGT bases. Alignment gaps are OTHER_BASE,
+    // so coordinates are unchanged
+    sequence
+        .bytes()
+        .map(|base| match base.to_ascii_uppercase() {
+            b'A' => 0,
+            b'T' | b'U' => 3,
+            b'C' => 1,
+            b'G' => 2,
+            _ => OTHER_BASE,
+        })
+        .collect()
+}
+
+fn encode_ungapped(sequence: &str) -> Vec<usize> {
+    // encode_reference, with alignment gaps dropped to give the genome of an aligned record
+    let ungapped: String = sequence
+        .chars()
+        .filter(|c| *c != '-' && *c != '.')
+        .collect();
+    encode_reference(&ungapped)
+}
+
+fn parse_records(contents: &str, encode: fn(&str) -> Vec<usize>) -> Vec<FastaRecord> {
+    let mut records: Vec<FastaRecord> = Vec::new();
+    for line in contents.lines() {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix('>') {
+            // The name is the first word of the header
+            let name = header.split_whitespace().next().unwrap_or("").to_string();
+            records.push(FastaRecord {
+                name,
+                seq: Vec::new(),
+            });
+        } else if let Some(record) = records.last_mut() {
+            record.seq.extend(encode(line));
+        }
+    }
+    records
+}
+
+pub fn parse_fasta(contents: &str) -> Vec<FastaRecord> {
+    parse_records(contents, encode_reference)
+}
+
+pub fn parse_alignment(contents: &str) -> Vec<FastaRecord> {
+    // parse_fasta, but each aligned record becomes its ungapped genome
+    parse_records(contents, encode_ungapped)
+}
+
+pub fn read_fasta(path: &str) -> io::Result<Vec<FastaRecord>> {
+    Ok(parse_fasta(&fs::read_to_string(path)?))
+}
+
+pub fn read_alignment(path: &str) -> io::Result<Vec<FastaRecord>> {
+    Ok(parse_alignment(&fs::read_to_string(path)?))
+}
+
+/// Where a primer binds on a reference record
+#[derive(Debug, Clone, PartialEq)]
+pub struct BindingSite {
+    pub site: Site,
+    pub score: f64,
+    // The reference on the primer's strand, 5'-3'. Identical to the primer for a perfect site
+    pub target: Vec<usize>,
+}
+
+pub fn reverse_complement(seq: &[usize]) -> Vec<usize> {
+    seq.iter()
+        .rev()
+        .map(|base| match *base {
+            OTHER_BASE => OTHER_BASE,
+            b => 3 - b,
+        })
+        .collect()
+}
+
+fn scan_strand(
+    primer: &[usize],
+    target: &[usize],
+    table: &NnTable,
+    t: f64,
+    params: &ScoringParams,
+) -> Vec<(usize, f64)> {
+    // Scores the primer against every window of the target, where the target is on the
+    // primer's strand. The template is scored with table. Returns (start, score) of
+    // each window scoring <= t.
+    // A site whose 3' end is the target's last base is never returned, as there is no
+    // template past the 3' end to extend onto. Nor is a primer overhanging the target's start
+    let len = primer.len();
+    let mut hits = Vec::new();
+    if len < 2 || target.len() < len + 1 {
+        return hits;
+    }
+
+    // The template the primer anneals to, aligned 3'-5' as calc_at_offset expects
+    let template: Vec<usize> = target
+        .iter()
+        .map(|base| match *base {
+            OTHER_BASE => OTHER_BASE,
+            b => 3 - b,
+        })
+        .collect();
+
+    // Running count of non ACGT bases, so windows containing them can be skipped
+    let mut other_count = vec![0; template.len() + 1];
+    for (i, base) in template.iter().enumerate() {
+        other_count[i + 1] = other_count[i] + (*base == OTHER_BASE) as usize;
+    }
+
+    // Each window includes the base past the primer's 3' end, which it extends onto
+    for start in 0..template.len() - len {
+        let end = start + len + 1;
+        if other_count[end] != other_count[start] {
+            continue;
+        }
+        let window = &template[start..end];
+        // Cheap guard for calc_extention, which needs a match in the final two 3' bases
+        if !MATCH_ARRAY[primer[len - 1]][window[len - 1]]
+            && !MATCH_ARRAY[primer[len - 2]][window[len - 2]]
+        {
+            continue;
+        }
+        if let Some(score) = calc_at_offset_table(primer, window, 0, table, params) {
+            if score <= t {
+                hits.push((start, score));
+            }
+        }
+    }
+    hits
+}
+
+pub fn scan_reference_acids(
+    primer: &[usize],
+    reference: &[usize],
+    acids: (NucleicAcid, NucleicAcid),
+    t: f64,
+    params: &ScoringParams,
+) -> Vec<BindingSite> {
+    // Finds every site on either strand where the primer binds with a score <= t.
+    // acids are those of the primer and the reference. An RNA reference is the template
+    // of reverse strand sites. Forward strand sites bind its cDNA, which is DNA
+    let mut sites: Vec<BindingSite> = Vec::new();
+    let len = primer.len();
+
+    let forward_table = nn_table((acids.0, NucleicAcid::Dna));
+    for (start, score) in scan_strand(primer, reference, forward_table, t, params) {
+        sites.push(BindingSite {
+            site: Site {
+                start,
+                end: start + len,
+                strand: Strand::Forward,
+            },
+            score,
+            target: reference[start..start + len].to_vec(),
+        });
+    }
+
+    // Scan the reverse strand, and convert back into reference coordinates
+    let reference_rc = reverse_complement(reference);
+    let reverse_table = nn_table(acids);
+    for (rc_start, score) in scan_strand(primer, &reference_rc, reverse_table, t, params) {
+        let start = reference.len() - rc_start - len;
+        sites.push(BindingSite {
+            site: Site {
+                start,
+                end: start + len,
+                strand: Strand::Reverse,
+            },
+            score,
+            target: reference_rc[rc_start..rc_start + len].to_vec(),
+        });
+    }
+    sites
+}
+
+pub fn format_alignment(primer: &[usize], target: &[usize]) -> String {
+    // 5'-PRIMER-3'
+    //    ||||.|
+    // 3'-TEMPLATE-5'
+    let primer_str = super::decode_base(primer);
+    let template: Vec<usize> = target.iter().map(|b| 3 - b).collect();
+    let template_str = super::decode_base(&template);
+    let matches: String = primer
+        .iter()
+        .zip(template.iter())
+        .map(|(p, t)| match MATCH_ARRAY[*p][*t] {
+            true => '|',
+            false => '.',
+        })
+        .collect();
+    format!(
+        "5'-{}-3'\n   {}\n3'-{}-5'",
+        primer_str, matches, template_str
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{encode_base, DEFAULT_PARAMS};
+    use super::*;
+
+    fn scan_dna(primer: &[usize], reference: &[usize], t: f64) -> Vec<BindingSite> {
+        let acids = (NucleicAcid::Dna, NucleicAcid::Dna);
+        scan_reference_acids(primer, reference, acids, t, &DEFAULT_PARAMS)
+    }
+    #[test]
+    fn test_parse_fasta() {
+        let records = parse_fasta(">chr1 some description\nACGT\nacgn\n>chr2\nT--T\n");
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].name, "chr1");
+        assert_eq!(records[0].seq, vec![0, 1, 2, 3, 0, 1, 2, OTHER_BASE]);
+        // Gaps keep their place
+        assert_eq!(records[1].seq, vec![3, OTHER_BASE, OTHER_BASE, 3]);
+    }
+    #[test]
+    fn test_parse_alignment() {
+        let records = parse_alignment(">genome1\nAC-GT\n>genome2\nA.CG--T\n");
+        assert_eq!(records[0].seq, encode_base("ACGT"));
+        assert_eq!(records[1].seq, encode_base("ACGT"));
+    }
+    #[test]
+    fn test_scan_both_strands() {
+        // nCoV-2019_76_RIGHT_0
+        let primer = encode_base("ACACCTGTGCCTGTTAAACCAT");
+        let flank = encode_base("GGGGGGGGGGGGGGGGGGGG");
+        let rc: Vec<usize> = primer.iter().rev().map(|b| 3 - b).collect();
+
+        // Forward site at 20, reverse site at 62
+        let mut reference = flank.clone();
+        reference.extend(&primer);
+        reference.extend(&flank);
+        reference.extend(&rc);
+        reference.extend(&flank);
+
+        let sites = scan_dna(&primer, &reference, -40.);
+        let found: Vec<(Site, Vec<usize>)> =
+            sites.iter().map(|s| (s.site, s.target.clone())).collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    Site {
+                        start: 20,
+                        end: 42,
+                        strand: Strand::Forward
+                    },
+                    primer.clone()
+                ),
+                (
+                    Site {
+                        start: 62,
+                        end: 84,
+                        strand: Strand::Reverse
+                    },
+                    primer.clone()
+                ),
+            ]
+        );
+    }
+    #[test]
+    fn test_scan_rna_reference() {
+        // Only the reverse site binds the RNA itself, so only its score changes
+        let primer = encode_base("ACACCTGTGCCTGTTAAACCAT");
+        let flank = encode_base("GGGGGGGGGGGGGGGGGGGG");
+        let rc: Vec<usize> = primer.iter().rev().map(|b| 3 - b).collect();
+        let mut reference = flank.clone();
+        reference.extend(&primer);
+        reference.extend(&flank);
+        reference.extend(&rc);
+        reference.extend(&flank);
+        assert_eq!(encode_reference("ACGU"), encode_base("ACGT"));
+
+        let dna = scan_dna(&primer, &reference, -40.);
+        let rna = scan_reference_acids(
+            &primer,
+            &reference,
+            (NucleicAcid::Dna, NucleicAcid::Rna),
+            -40.,
+            &DEFAULT_PARAMS,
+        );
+        assert_eq!(rna.len(), 2);
+        assert_eq!(rna[0], dna[0]);
+        assert_eq!(rna[1].site, dna[1].site);
+        assert_ne!(rna[1].score, dna[1].score);
+    }
+    #[test]
+    fn test_scan_record_edges() {
+        // A site needs a template base past its 3' end, on either strand
+        let primer = encode_base("ACACCTGTGCCTGTTAAACCAT");
+        let rc: Vec<usize> = primer.iter().rev().map(|b| 3 - b).collect();
+        for (reference, strand) in [(primer.clone(), Strand::Forward), (rc, Strand::Reverse)] {
+            assert!(scan_dna(&primer, &reference, -40.).is_empty());
+
+            let mut extended = reference.clone();
+            match strand {
+                Strand::Forward => extended.push(0),
+                Strand::Reverse => extended.insert(0, 0),
+            }
+            let sites = scan_dna(&primer, &extended, -40.);
+            assert_eq!(sites.len(), 1);
+            assert_eq!(sites[0].site.strand, strand);
+        }
+    }
+    #[test]
+    fn test_skip_other_bases() {
+        let primer = encode_base("ACACCTGTGCCTGTTAAACCAT");
+        let mut reference = primer.clone();
+        reference.push(0);
+        reference[5] = OTHER_BASE;
+        assert!(scan_dna(&primer, &reference, 0.).is_empty());
+    }
+    #[test]
+    fn test_format_alignment() {
+        let primer = encode_base("ACGT");
+        let target = encode_base("ACCT");
+        assert_eq!(
+            format_alignment(&primer, &target),
+            "5'-ACGT-3'\n   ||.|\n3'-TGGA-5'"
+        );
+    }
+}
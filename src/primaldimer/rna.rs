@@ -0,0 +1,175 @@
+use std::sync::OnceLock;
+
+use super::scores::NnTable;
+use super::tm::HYBRID_NN_ARRAY;
+use super::NN_SCORES;
+
+// Body temperature, for the free energy of the hybrid stacks
+const T_37: f64 = 310.15;
+
+// Xia et al. (1998) RNA/RNA dG37 for Watson-Crick pairs
+// RNA_NN_DG[5' base][3' base], with U encoded as T
+static RNA_NN_DG: [[f64; 4]; 4] = [
+    [-0.93, -2.24, -2.08, -1.10],
+    [-2.11, -3.26, -2.36, -2.08],
+    [-2.35, -3.42, -3.26, -2.24],
+    [-1.33, -2.35, -2.11, -0.93],
+];
+
+/// The backbone of a seq
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum NucleicAcid {
+    Dna,
+    Rna,
+}
+
+impl NucleicAcid {
+    pub fn try_of(seq: &str) -> Result<NucleicAcid, String> {
+        // A seq containing U is RNA. A seq can't contain both T and U
+        match (seq.contains('T'), seq.contains('U')) {
+            (true, true) => Err(format!("Sequence mixes T and U: {}", seq)),
+            (false, true) => Ok(NucleicAcid::Rna),
+            _ => Ok(NucleicAcid::Dna),
+        }
+    }
+
+    pub fn of(seq: &str) -> NucleicAcid {
+        // try_of, panicking on a seq that mixes T and U
+        NucleicAcid::try_of(seq).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+fn hybrid_dg(a: usize, b: usize) -> f64 {
+    // dG37 of the hybrid stack, from the DNA strand's 5'-3' bases
+    let (dh, ds) = HYBRID_NN_ARRAY[a][b];
+    dh - T_37 * ds / 1000.
+}
+
+fn with_stacks(dg: impl Fn(usize, usize) -> f64) -> NnTable {
+    // NN_SCORES with the Watson-Crick stacks replaced. Mismatches keep the DNA values
+    let mut table = NN_SCORES;
+    for a in 0..4 {
+        for b in 0..4 {
+            table[a][b][3 - a][3 - b] = Some(dg(a, b));
+        }
+    }
+    table
+}
+
+pub fn nn_table(acids: (NucleicAcid, NucleicAcid)) -> &'static NnTable {
+    // The NN stacks for seq1 (5'-3') of acids.0 paired with seq2 (3'-5') of acids.1
+    static DNA_RNA: OnceLock<NnTable> = OnceLock::new();
+    static RNA_DNA: OnceLock<NnTable> = OnceLock::new();
+    static RNA_RNA: OnceLock<NnTable> = OnceLock::new();
+
+    match acids {
+        (NucleicAcid::Dna, NucleicAcid::Dna) => &NN_SCORES,
+        (NucleicAcid::Dna, NucleicAcid::Rna) => DNA_RNA.get_or_init(|| with_stacks(hybrid_dg)),
+        // The DNA strand is seq2, so read its 5'-3' bases from seq1's complement
+        (NucleicAcid::Rna, NucleicAcid::Dna) => {
+            RNA_DNA.get_or_init(|| with_stacks(|a, b| hybrid_dg(3 - b, 3 - a)))
+        }
+        (NucleicAcid::Rna, NucleicAcid::Rna) => {
+            RNA_RNA.get_or_init(|| with_stacks(|a, b| RNA_NN_DG[a][b]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        calc_at_offset_table, calc_kmers_min_score, calc_seqs_min_score_acids, do_kmers_interact,
+        encode_base, predict_dimer_product, KmerSeqs, TailPolicy, DEFAULT_PARAMS,
+    };
+    use super::*;
+    const DNA: NucleicAcid = NucleicAcid::Dna;
+    const RNA: NucleicAcid = NucleicAcid::Rna;
+
+    #[test]
+    fn test_encode_rna() {
+        assert_eq!(encode_base("ACGU"), encode_base("ACGT"));
+        assert_eq!(NucleicAcid::of("ACGU"), RNA);
+        assert_eq!(NucleicAcid::of("ACGT"), DNA);
+        assert!(NucleicAcid::try_of("ACGTU").is_err());
+    }
+    #[test]
+    #[should_panic]
+    fn test_mixed_acid() {
+        NucleicAcid::of("ACGTU");
+    }
+    #[test]
+    fn test_hybrid_stacks() {
+        // Sugimoto et al. (1995) dG37 of rGG/dCC and rUU/dAA
+        let table = nn_table((DNA, RNA));
+        assert!((table[1][1][2][2].unwrap() - -2.9).abs() < 0.01);
+        assert!((table[0][0][3][3].unwrap() - -0.2).abs() < 0.02);
+        // The same stacks, with the RNA as seq1
+        let table = nn_table((RNA, DNA));
+        assert_eq!(table[2][2][1][1], nn_table((DNA, RNA))[1][1][2][2]);
+        assert_eq!(table[3][3][0][0], nn_table((DNA, RNA))[0][0][3][3]);
+        // Mismatches are unchanged
+        assert_eq!(table[0][1][3][3], NN_SCORES[0][1][3][3]);
+        // RNA/RNA stacks are symmetric
+        let table = nn_table((RNA, RNA));
+        assert_eq!(table[0][1][3][2], table[2][3][1][0]);
+    }
+    #[test]
+    fn test_dna_unchanged() {
+        let s1 = encode_base("ACACCTGTGCCTGTTAAACCAT");
+        let s2 = encode_base("GGCACAGGTGTAAA");
+        let mut s2_rev = s2.clone();
+        s2_rev.reverse();
+        for offset in -20..20 {
+            assert_eq!(
+                calc_at_offset_table(&s1, &s2_rev, offset, nn_table((DNA, DNA)), &DEFAULT_PARAMS),
+                calc_at_offset_table(&s1, &s2_rev, offset, &NN_SCORES, &DEFAULT_PARAMS)
+            );
+        }
+        assert_eq!(
+            calc_seqs_min_score_acids(&s1, &s2, (DNA, DNA), (true, true), &DEFAULT_PARAMS),
+            calc_seqs_min_score_acids(&s1, &s2, (DNA, DNA), (true, true), &DEFAULT_PARAMS)
+        );
+    }
+    #[test]
+    fn test_rna_changes_score() {
+        // An AT rich duplex is weaker with rU/dA pairs, and symmetric in the order of the seqs
+        let s1 = encode_base("GCAAAAAAAAAAAAAC");
+        let s2 = encode_base("GTTTTTTTTTTTTTTGC");
+        let score =
+            |acids| calc_seqs_min_score_acids(&s1, &s2, acids, (true, true), &DEFAULT_PARAMS);
+        let dna = score((DNA, DNA)).unwrap();
+        let hybrid = score((DNA, RNA)).unwrap();
+        assert!(hybrid > dna);
+        assert_eq!(
+            calc_seqs_min_score_acids(&s2, &s1, (RNA, DNA), (true, true), &DEFAULT_PARAMS),
+            Some(hybrid)
+        );
+    }
+    #[test]
+    fn test_rna_kmers() {
+        // Kmers, products and genome binding are scored with the kmer's acid
+        let s1 = [encode_base("GCAAAAAAAAAAAAAC")];
+        let s2 = [encode_base("GTTTTTTTTTTTTTTGC")];
+        let (dna, mut rna) = (KmerSeqs::new(&s1), KmerSeqs::new(&s2));
+        rna.acid = RNA;
+        let policy = TailPolicy::default();
+        let hybrid =
+            calc_seqs_min_score_acids(&s1[0], &s2[0], (DNA, RNA), (true, true), &DEFAULT_PARAMS);
+        assert_eq!(
+            calc_kmers_min_score(&dna, &rna, &policy, &DEFAULT_PARAMS),
+            hybrid
+        );
+
+        // A threshold between the DNA and hybrid scores
+        let dna_score =
+            calc_seqs_min_score_acids(&s1[0], &s2[0], (DNA, DNA), (true, true), &DEFAULT_PARAMS)
+                .unwrap();
+        let t = (dna_score + hybrid.unwrap()) / 2.;
+        let dna2 = KmerSeqs::new(&s2);
+        assert!(do_kmers_interact(&dna, &dna2, t, &policy, &DEFAULT_PARAMS));
+        assert!(!do_kmers_interact(&dna, &rna, t, &policy, &DEFAULT_PARAMS));
+
+        let product = predict_dimer_product(&s1[0], &s2[0], (DNA, RNA), &DEFAULT_PARAMS);
+        assert_eq!(product.map(|p| p.score), hybrid);
+    }
+}
@@ -1,4 +1,8 @@
-pub static NN_SCORES: [[[[Option<f64>; 4]; 4]; 4]; 4] = [
+// NN_SCORES[seq1 base][next seq1 base][seq2 base][next seq2 base], seq2 3'-5'.
+// None for a double mismatch
+pub type NnTable = [[[[Option<f64>; 4]; 4]; 4]; 4];
+
+pub static NN_SCORES: NnTable = [
     [
         [
             [None, None, None, Some(0.69)],
@@ -0,0 +1,279 @@
+use super::rna::nn_table;
+use super::scores::NnTable;
+use super::{
+    calc_at_offset_breakdown_table, calc_seqs_min_score_acids, extension_offsets, NucleicAcid,
+    ScoringParams,
+};
+
+/// How interactions involving 5' tails are weighted. 1 scores them as
+/// ordinary seq, 0 masks them. A weight between them only scales the NN stacks;
+/// the match bonuses and extension count any position above 0 as ordinary seq
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TailPolicy {
+    // Both bases of an aligned position are in a tail
+    pub tail_tail: f64,
+    // Only one of them is
+    pub tail_primer: f64,
+}
+
+impl Default for TailPolicy {
+    fn default() -> Self {
+        // Tails can't pair with each other, but primers can still bind them
+        TailPolicy {
+            tail_tail: 0.,
+            tail_primer: 1.,
+        }
+    }
+}
+
+impl TailPolicy {
+    pub fn new(tail_tail: f64, tail_primer: f64) -> Result<TailPolicy, String> {
+        // Weights must be between 0 and 1 (inclusive)
+        for (name, weight) in [("tail_tail", tail_tail), ("tail_primer", tail_primer)] {
+            if !(0. ..=1.).contains(&weight) {
+                return Err(format!("{} must be between 0 and 1: {}", name, weight));
+            }
+        }
+        Ok(TailPolicy {
+            tail_tail,
+            tail_primer,
+        })
+    }
+
+    pub fn weight(&self, seq1_tail: bool, seq2_tail: bool) -> f64 {
+        match (seq1_tail, seq2_tail) {
+            (true, true) => self.tail_tail,
+            (true, false) | (false, true) => self.tail_primer,
+            (false, false) => 1.,
+        }
+    }
+
+    pub fn is_unweighted(&self, tails: (usize, usize)) -> bool {
+        // If every position has a weight of 1, so the seqs score as if untailed
+        tails == (0, 0) || (self.tail_tail == 1. && self.tail_primer == 1.)
+    }
+}
+
+pub fn calc_at_offset_tailed(
+    seq1: &[usize],
+    seq2: &[usize],
+    tails: (usize, usize),
+    offset: i32,
+    policy: &TailPolicy,
+    table: &NnTable,
+    params: &ScoringParams,
+) -> Option<f64> {
+    // calc_at_offset_table, where the first tails.0 bases of seq1 and the last tails.1 bases
+    // of seq2 (its 5' end, as seq2 is reversed) are tails weighted by policy
+    calc_at_offset_breakdown_table(seq1, seq2, offset, table, tails, policy, false, params)
+        .map(|b| b.score)
+}
+
+fn seq1_best_score_tailed(
+    seq1: &[usize],
+    seq2: &[usize],
+    tails: (usize, usize),
+    acids: (NucleicAcid, NucleicAcid),
+    policy: &TailPolicy,
+    params: &ScoringParams,
+) -> Option<f64> {
+    // tails are the 5' tail lengths of seq1 and seq2, both given 5'-3'
+    let table = nn_table(acids);
+    let mut seq2_rev = seq2.to_owned();
+    seq2_rev.reverse();
+
+    extension_offsets(seq1, seq2)
+        .filter_map(|offset| {
+            calc_at_offset_tailed(seq1, &seq2_rev, tails, offset, policy, table, params)
+        })
+        .min_by(|a, b| a.total_cmp(b))
+}
+
+pub fn calc_seqs_min_score_tailed(
+    seq1: &[usize],
+    seq2: &[usize],
+    tails: (usize, usize),
+    acids: (NucleicAcid, NucleicAcid),
+    extendable: (bool, bool),
+    policy: &TailPolicy,
+    params: &ScoringParams,
+) -> Option<f64> {
+    // calc_seqs_min_score_acids, with a 5' tail of tails.0 and tails.1 bases on each seq
+    if policy.is_unweighted(tails) {
+        return calc_seqs_min_score_acids(seq1, seq2, acids, extendable, params);
+    }
+    let best_score =
+        |extendable: bool, seq1: &[usize], seq2: &[usize], tails, acids| match extendable {
+            true => seq1_best_score_tailed(seq1, seq2, tails, acids, policy, params),
+            false => None,
+        };
+    let s1_score = best_score(extendable.0, seq1, seq2, tails, acids);
+    let s2_score = best_score(
+        extendable.1,
+        seq2,
+        seq1,
+        (tails.1, tails.0),
+        (acids.1, acids.0),
+    );
+
+    match (s1_score, s2_score) {
+        (Some(s1), Some(s2)) => Some(s1.min(s2)),
+        (s1, s2) => s1.or(s2),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        calc_at_offset_table, calc_kmers_min_score, calc_kmers_min_score_below,
+        calc_seqs_min_score_acids, do_kmers_interact, encode_base, try_calc_at_offset,
+        InteractionGraph, KmerSeqs, DEFAULT_PARAMS, NN_SCORES,
+    };
+    use super::*;
+
+    const DNA_PAIR: (NucleicAcid, NucleicAcid) = (NucleicAcid::Dna, NucleicAcid::Dna);
+
+    const SCORE_TAILS: TailPolicy = TailPolicy {
+        tail_tail: 1.,
+        tail_primer: 1.,
+    };
+    const MASK_TAILS: TailPolicy = TailPolicy {
+        tail_tail: 0.,
+        tail_primer: 0.,
+    };
+
+    #[test]
+    fn test_untailed_unchanged() {
+        let seq1 = encode_base("ACACCTGTGCCTGTTAAACCAT");
+        let mut seq2 = encode_base("TGGAAATACCCACAAGTTAATGGTTTAAC");
+        let tails = [(0, 0), (5, 8)];
+        seq2.reverse();
+        for offset in -30..30 {
+            let expected = calc_at_offset_table(&seq1, &seq2, offset, &NN_SCORES, &DEFAULT_PARAMS);
+            // No tails, or tails that are scored as ordinary seq
+            for (tails, policy) in [(tails[0], &MASK_TAILS), (tails[1], &SCORE_TAILS)] {
+                let score = calc_at_offset_tailed(
+                    &seq1,
+                    &seq2,
+                    tails,
+                    offset,
+                    policy,
+                    &NN_SCORES,
+                    &DEFAULT_PARAMS,
+                );
+                assert_eq!(score.map(f64::to_bits), expected.map(f64::to_bits));
+            }
+        }
+    }
+    #[test]
+    fn test_try_tailed() {
+        // try_calc_at_offset weights the tails it is given
+        let seq1 = encode_base("ACACCTGTGCCTGTTAAACCAT");
+        let mut seq2 = encode_base("TGGAAATACCCACAAGTTAATGGTTTAAC");
+        seq2.reverse();
+        let policy = weighted_policy();
+        let mut changed = false;
+        for offset in -30..30 {
+            let tailed = |tails| {
+                try_calc_at_offset(
+                    &seq1,
+                    &seq2,
+                    offset,
+                    DNA_PAIR,
+                    tails,
+                    &policy,
+                    &DEFAULT_PARAMS,
+                )
+            };
+            let expected = calc_at_offset_tailed(
+                &seq1,
+                &seq2,
+                (5, 8),
+                offset,
+                &policy,
+                &NN_SCORES,
+                &DEFAULT_PARAMS,
+            );
+            assert_eq!(tailed((5, 8)), Ok(expected));
+            changed |= tailed((0, 0)) != Ok(expected);
+        }
+        assert!(changed);
+    }
+    #[test]
+    fn test_mask_adapter_dimer() {
+        // seq1's 3' end is complementary to the tail of seq2
+        let tail = "ACACTCTTTCCCTACACGAC";
+        let seq1 = encode_base(&format!("{}TTGCAGTGTCGTGTAGG", tail));
+        let seq2 = encode_base(&format!("{}CCTTAGCGAAGATCCA", tail));
+        let tail_len = tail.len();
+
+        let tails = (tail_len, tail_len);
+        let min_score = |policy, extendable| {
+            calc_seqs_min_score_tailed(
+                &seq1,
+                &seq2,
+                tails,
+                DNA_PAIR,
+                extendable,
+                policy,
+                &DEFAULT_PARAMS,
+            )
+        };
+        let scored = min_score(&SCORE_TAILS, (true, true));
+        assert_eq!(
+            scored,
+            calc_seqs_min_score_acids(&seq1, &seq2, DNA_PAIR, (true, true), &DEFAULT_PARAMS)
+        );
+        assert!(scored.unwrap() < -26.);
+
+        // Down weighting the tail raises the score, masking it removes the dimer
+        let weighted = weighted_policy();
+        let weighted = min_score(&weighted, (true, true)).unwrap();
+        assert!(weighted > scored.unwrap());
+        assert!(!matches!(min_score(&MASK_TAILS, (true, true)), Some(s) if s <= -26.));
+
+        // Only seq1 extends onto the tail
+        assert_eq!(min_score(&weighted_policy(), (true, false)), Some(weighted));
+    }
+    fn weighted_policy() -> TailPolicy {
+        TailPolicy::new(0., 0.5).unwrap()
+    }
+    #[test]
+    fn test_policy_bounds() {
+        assert_eq!(TailPolicy::new(0., 1.), Ok(TailPolicy::default()));
+        for (tail_tail, tail_primer) in [(-0.1, 1.), (0., 1.5), (f64::NAN, 1.)] {
+            assert!(TailPolicy::new(tail_tail, tail_primer).is_err());
+        }
+    }
+    #[test]
+    fn test_tailed_kmers() {
+        // The shared kmer functions and the graph weight each kmer's tail
+        let tail = "ACACTCTTTCCCTACACGAC";
+        let seqs1 = [encode_base(&format!("{}TTGCAGTGTCGTGTAGG", tail))];
+        let seqs2 = [encode_base(&format!("{}CCTTAGCGAAGATCCA", tail))];
+        let (mut kmer1, mut kmer2) = (KmerSeqs::new(&seqs1), KmerSeqs::new(&seqs2));
+        let interacts = |kmer1: &KmerSeqs, kmer2: &KmerSeqs, policy: &TailPolicy| {
+            let below = calc_kmers_min_score_below(kmer1, kmer2, -26., policy, &DEFAULT_PARAMS);
+            let min_score = calc_kmers_min_score(kmer1, kmer2, policy, &DEFAULT_PARAMS);
+            assert_eq!(below, min_score.filter(|s| *s <= -26.));
+            assert_eq!(
+                do_kmers_interact(kmer1, kmer2, -26., policy, &DEFAULT_PARAMS),
+                below.is_some()
+            );
+            below.is_some()
+        };
+        let graph_edges = |kmer1: &KmerSeqs, kmer2: &KmerSeqs| {
+            InteractionGraph::from_pools(&[*kmer1], &[*kmer2], -26., &DEFAULT_PARAMS)
+                .edges
+                .len()
+        };
+        assert!(interacts(&kmer1, &kmer2, &MASK_TAILS));
+        kmer1.tail_len = tail.len();
+        kmer2.tail_len = tail.len();
+        // seq1's 3' end binds the tail of seq2, which is only masked if tail_primer is 0
+        assert!(!interacts(&kmer1, &kmer2, &MASK_TAILS));
+        assert!(interacts(&kmer1, &kmer2, &TailPolicy::default()));
+        // The graph uses the default policy
+        assert_eq!(graph_edges(&kmer1, &kmer2), 1);
+    }
+}
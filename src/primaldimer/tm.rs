@@ -0,0 +1,98 @@
+// SantaLucia (1998) unified nearest neighbour parameters for Watson-Crick pairs
+// TM_NN_ARRAY[5' base][3' base] = (dH kcal/mol, dS cal/K/mol)
+// base_to_encode = {"A": 0, "T": 3, "C": 1, "G": 2}
+static TM_NN_ARRAY: [[(f64, f64); 4]; 4] = [
+    [(-7.9, -22.2), (-8.4, -22.4), (-7.8, -21.0), (-7.2, -20.4)],
+    [(-8.5, -22.7), (-8.0, -19.9), (-10.6, -27.2), (-7.8, -21.0)],
+    [(-8.2, -22.2), (-9.8, -24.4), (-8.0, -19.9), (-8.4, -22.4)],
+    [(-7.2, -21.3), (-8.2, -22.2), (-8.5, -22.7), (-7.9, -22.2)],
+];
+// Initiation with a terminal G/C or A/T pair
+static TM_INIT_GC: (f64, f64) = (0.1, -2.8);
+static TM_INIT_AT: (f64, f64) = (2.3, 4.1);
+
+// Sugimoto et al. (1995) RNA/DNA hybrid parameters for Watson-Crick pairs
+// HYBRID_NN_ARRAY[5' base][3' base] of the DNA strand = (dH kcal/mol, dS cal/K/mol)
+pub static HYBRID_NN_ARRAY: [[(f64, f64); 4]; 4] = [
+    [(-11.5, -36.4), (-7.8, -21.6), (-7.0, -19.7), (-8.3, -23.9)],
+    [
+        (-10.4, -28.4),
+        (-12.8, -31.9),
+        (-16.3, -47.1),
+        (-9.1, -23.5),
+    ],
+    [(-8.6, -22.9), (-8.0, -17.1), (-9.3, -23.2), (-5.9, -12.3)],
+    [(-7.8, -23.2), (-5.5, -13.5), (-9.0, -26.1), (-7.8, -21.9)],
+];
+// A single initiation, whatever the terminal pairs
+static HYBRID_INIT: (f64, f64) = (1.9, -3.9);
+
+// Reaction conditions
+const NA_CONC: f64 = 0.05; // Molar
+const OLIGO_CONC: f64 = 250e-9; // Molar
+const GAS_CONSTANT: f64 = 1.9872; // cal/K/mol
+
+pub fn calc_tm(seq: &[usize]) -> f64 {
+    // The melting temperature (C) of seq against its perfect complement
+    let (mut dh, mut ds) = sum_stacks(seq, &TM_NN_ARRAY);
+    for terminal in [seq.first(), seq.last()].into_iter().flatten() {
+        let (h, s) = match terminal {
+            1 | 2 => TM_INIT_GC,
+            _ => TM_INIT_AT,
+        };
+        dh += h;
+        ds += s;
+    }
+    tm_from_thermo(dh, ds, seq.len())
+}
+
+pub fn calc_tm_hybrid(seq: &[usize]) -> f64 {
+    // The melting temperature (C) of the DNA seq against its complementary RNA,
+    // as an RT primer on an RNA template
+    let (dh, ds) = sum_stacks(seq, &HYBRID_NN_ARRAY);
+    tm_from_thermo(dh + HYBRID_INIT.0, ds + HYBRID_INIT.1, seq.len())
+}
+
+fn sum_stacks(seq: &[usize], nn_array: &[[(f64, f64); 4]; 4]) -> (f64, f64) {
+    let mut dh = 0.;
+    let mut ds = 0.;
+    for pair in seq.windows(2) {
+        let (h, s) = nn_array[pair[0]][pair[1]];
+        dh += h;
+        ds += s;
+    }
+    (dh, ds)
+}
+
+fn tm_from_thermo(dh: f64, ds: f64, len: usize) -> f64 {
+    // Salt correction of the entropy
+    let ds = ds + 0.368 * (len as f64 - 1.) * NA_CONC.ln();
+
+    (dh * 1000.) / (ds + GAS_CONSTANT * (OLIGO_CONC / 4.).ln()) - 273.15
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::encode_base;
+    use super::*;
+    #[test]
+    fn test_tm() {
+        // nCoV-2019_76_RIGHT_0
+        let tm = calc_tm(&encode_base("ACACCTGTGCCTGTTAAACCAT"));
+        assert!((tm - 56.50).abs() < 0.01);
+    }
+    #[test]
+    fn test_hybrid_tm() {
+        // dA/rU pairs are much weaker than dA/dT
+        let seq = encode_base("AAAAAAAAAAAAAAAAAAAA");
+        assert!(calc_tm_hybrid(&seq) < calc_tm(&seq) - 10.);
+        let tm = calc_tm_hybrid(&encode_base("ACACCTGTGCCTGTTAAACCAT"));
+        assert!((tm - calc_tm(&encode_base("ACACCTGTGCCTGTTAAACCAT"))).abs() < 15.);
+    }
+    #[test]
+    fn test_gc_raises_tm() {
+        let at = calc_tm(&encode_base("ATATATATATATATATATAT"));
+        let gc = calc_tm(&encode_base("GCGCGCGCGCGCGCGCGCGC"));
+        assert!(gc > at);
+    }
+}
@@ -0,0 +1,171 @@
+use super::logistic::{fit_logistic, logistic};
+use super::{calc_seqs_min_score_acids, NucleicAcid, ScoringParams};
+
+// Indices into ScoringParams.bonus that the scoring function uses.
+// 3-6 are not read by calc_at_offset so fitting them would have no effect
+static FITTED_PARAMS: [usize; 6] = [0, 1, 2, 7, 8, 9];
+
+// Clamp probabilities to avoid ln(0)
+const EPS: f64 = 1e-9;
+
+// Two encoded seqs, and the acid of each
+pub type EncodedPair = (Vec<usize>, Vec<usize>, (NucleicAcid, NucleicAcid));
+
+/// The coefficients from fit_params, with the logistic fitted alongside them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FittedParams {
+    pub params: ScoringParams,
+    pub log_loss: f64,
+}
+
+pub fn calc_log_loss(
+    pairs: &[EncodedPair],
+    labels: &[bool],
+    params: &ScoringParams,
+) -> (f64, [f64; 2]) {
+    // Mean log loss of each pair against its label, with the logistic from score to probability
+    // fitted to these scores. Returns the loss and the logistic's intercept and slope
+    let scores: Vec<Option<f64>> = pairs
+        .iter()
+        .map(|(seq1, seq2, acids)| {
+            calc_seqs_min_score_acids(seq1, seq2, *acids, (true, true), params)
+        })
+        .collect();
+    let (scored, scored_labels): (Vec<f64>, Vec<bool>) = scores
+        .iter()
+        .zip(labels.iter())
+        .filter_map(|(score, label)| score.map(|s| (s, *label)))
+        .unzip();
+    let coefs = fit_logistic(&scored, &scored_labels);
+
+    let mut loss = 0.;
+    for (score, label) in scores.iter().zip(labels.iter()) {
+        // Pairs that can't extend have no chance of forming a dimer
+        let p = match score {
+            Some(score) => logistic(&coefs, *score).clamp(EPS, 1. - EPS),
+            None => EPS,
+        };
+        loss -= match label {
+            true => p.ln(),
+            false => (1. - p).ln(),
+        };
+    }
+    (loss / pairs.len() as f64, coefs)
+}
+
+pub fn fit_params(
+    pairs: &[EncodedPair],
+    labels: &[bool],
+    initial: &ScoringParams,
+    max_iter: usize,
+) -> FittedParams {
+    // Pattern search over the coefficients. The min over offsets makes the loss
+    // non-smooth, so this avoids gradients.
+    // Each iteration tries +/- step on each coefficient, halving the step when none improve.
+    // The logistic is refitted for every candidate, so it is fitted jointly with the coefficients
+    if pairs.len() != labels.len() {
+        panic!(
+            "Number of pairs ({}) and labels ({}) differ",
+            pairs.len(),
+            labels.len()
+        );
+    }
+    if pairs.is_empty() {
+        return FittedParams {
+            params: *initial,
+            log_loss: 0.,
+        };
+    }
+
+    let mut params = *initial;
+    let (mut best_loss, mut best_logistic) = calc_log_loss(pairs, labels, &params);
+    let mut step = 0.5;
+
+    for _ in 0..max_iter {
+        let mut improved = false;
+
+        for index in FITTED_PARAMS.iter() {
+            for direction in [1., -1.] {
+                let mut candidate = params;
+                candidate.bonus[*index] += direction * step;
+
+                let (loss, logistic) = calc_log_loss(pairs, labels, &candidate);
+                if loss < best_loss {
+                    best_loss = loss;
+                    best_logistic = logistic;
+                    params = candidate;
+                    improved = true;
+                    break;
+                }
+            }
+        }
+
+        if !improved {
+            step /= 2.;
+            if step < 1e-3 {
+                break;
+            }
+        }
+    }
+    FittedParams {
+        params: ScoringParams {
+            logistic: Some(best_logistic),
+            ..params
+        },
+        log_loss: best_loss,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{encode_base, DEFAULT_PARAMS};
+    use super::*;
+
+    const DNA_PAIR: (NucleicAcid, NucleicAcid) = (NucleicAcid::Dna, NucleicAcid::Dna);
+
+    fn labelled_pairs() -> (Vec<EncodedPair>, Vec<bool>) {
+        let pairs = [
+            // nCoV-2019_76_RIGHT_0 nCoV-2019_18_LEFT_0
+            ("ACACCTGTGCCTGTTAAACCAT", "TGGAAATACCCACAAGTTAATGGTTTAAC"),
+            ("CCAAACAAAGTTGGGTAAGGATAGATCAAT", "ACTCCCATGGCATAGCTCCAAA"),
+            ("CTCTTGTAGATCTGTTCTCTAAACGAACTTT", "ACTCCCATGGCATAGCTCCAGA"),
+        ];
+        let labels = vec![true, false, false];
+        let pairs = pairs
+            .iter()
+            .map(|(s1, s2)| (encode_base(s1), encode_base(s2), DNA_PAIR))
+            .collect();
+        (pairs, labels)
+    }
+
+    #[test]
+    fn test_fit_does_not_increase_loss() {
+        let (pairs, labels) = labelled_pairs();
+        let (initial_loss, _logistic) = calc_log_loss(&pairs, &labels, &DEFAULT_PARAMS);
+
+        let fitted = fit_params(&pairs, &labels, &DEFAULT_PARAMS, 20);
+        assert!(fitted.log_loss <= initial_loss);
+        assert_eq!(
+            calc_log_loss(&pairs, &labels, &fitted.params),
+            (fitted.log_loss, fitted.params.logistic.unwrap())
+        );
+
+        // Unused coefficients should be left alone
+        for index in 3..7 {
+            assert_eq!(fitted.params.bonus[index], DEFAULT_PARAMS.bonus[index]);
+        }
+    }
+    #[test]
+    fn test_fit_empty() {
+        assert_eq!(
+            fit_params(&[], &[], &DEFAULT_PARAMS, 20).params,
+            DEFAULT_PARAMS
+        );
+    }
+    #[test]
+    #[should_panic]
+    fn test_fit_mismatched_labels() {
+        let (pairs, _labels) = labelled_pairs();
+        fit_params(&pairs, &[true], &DEFAULT_PARAMS, 20);
+    }
+}
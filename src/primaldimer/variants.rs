@@ -0,0 +1,292 @@
+use super::reference::reverse_complement;
+use super::{
+    calc_extention, scan_reference_acids, BindingSite, NucleicAcid, ScoringParams, Site, Strand,
+};
+
+/// How well a kmer binds a single genome
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenomeBinding {
+    // The index of the kmer seq that binds best. None if no seq could be placed
+    pub seq_index: Option<usize>,
+    // Scored f64::INFINITY if it is the expected site and the seq can't extend there
+    pub site: Option<BindingSite>,
+    // Mismatch positions, counted from the 3' base (0)
+    pub mismatches: Vec<usize>,
+    // The calc_extention score of the site. None if neither 3' base matches
+    pub extension_score: Option<f64>,
+    // If the best site scores <= t
+    pub binds: bool,
+}
+
+fn find_best_site(
+    seq: &[usize],
+    acid: NucleicAcid,
+    genome: &[usize],
+    expected: Option<Site>,
+    padding: usize,
+    t: f64,
+    params: &ScoringParams,
+) -> Option<BindingSite> {
+    // Searches around the expected site if given, otherwise both strands of the whole genome.
+    // If no site around the expected site binds (score <= t), the seq is placed at the
+    // expected site instead, so its mismatches there are reported
+    let best = scan_sites(seq, acid, genome, expected, padding, params);
+    match expected {
+        Some(site) if !matches!(&best, Some(s) if s.score <= t) => {
+            let at_site = scan_sites(seq, acid, genome, expected, 0, params)
+                .filter(|s| three_prime_end(&s.site) == three_prime_end(&site));
+            at_site
+                .or_else(|| unscored_site(seq.len(), genome, site))
+                .or(best)
+        }
+        _ => best,
+    }
+}
+
+fn three_prime_end(site: &Site) -> usize {
+    match site.strand {
+        Strand::Forward => site.end,
+        Strand::Reverse => site.start,
+    }
+}
+
+fn unscored_site(len: usize, genome: &[usize], expected: Site) -> Option<BindingSite> {
+    // The len bases sharing the expected site's 3' end, which the seq can't extend from
+    let (start, end) = match expected.strand {
+        Strand::Forward => (expected.end.checked_sub(len)?, expected.end),
+        Strand::Reverse => (expected.start, expected.start + len),
+    };
+    let bases = genome.get(start..end)?;
+    let target = match expected.strand {
+        Strand::Forward => bases.to_vec(),
+        Strand::Reverse => reverse_complement(bases),
+    };
+    Some(BindingSite {
+        site: Site {
+            start,
+            end,
+            strand: expected.strand,
+        },
+        score: f64::INFINITY,
+        target,
+    })
+}
+
+fn scan_sites(
+    seq: &[usize],
+    acid: NucleicAcid,
+    genome: &[usize],
+    expected: Option<Site>,
+    padding: usize,
+    params: &ScoringParams,
+) -> Option<BindingSite> {
+    // The lowest scoring site within padding of the expected site, or on the whole genome.
+    // The genome is DNA. The search includes the base past the expected site's 3' end,
+    // which it extends onto, so a padding of 0 searches only the expected site
+    let (lo, hi) = match expected {
+        Some(site) => {
+            let (before, after) = match site.strand {
+                Strand::Forward => (padding, padding + 1),
+                Strand::Reverse => (padding + 1, padding),
+            };
+            (
+                site.start.saturating_sub(before),
+                (site.end + after).min(genome.len()),
+            )
+        }
+        None => (0, genome.len()),
+    };
+    if lo >= hi {
+        return None;
+    }
+
+    let acids = (acid, NucleicAcid::Dna);
+    scan_reference_acids(seq, &genome[lo..hi], acids, f64::INFINITY, params)
+        .into_iter()
+        .filter(|s| match expected {
+            Some(e) => e.strand == s.site.strand,
+            None => true,
+        })
+        .map(|mut s| {
+            s.site.start += lo;
+            s.site.end += lo;
+            s
+        })
+        .min_by(|a, b| a.score.total_cmp(&b.score))
+}
+
+pub fn calc_genome_binding(
+    seqs: &[Vec<usize>],
+    acid: NucleicAcid,
+    genome: &[usize],
+    expected: Option<Site>,
+    padding: usize,
+    t: f64,
+    params: &ScoringParams,
+) -> GenomeBinding {
+    // Finds the best site of any seq in the kmer, and describes its mismatches
+    let best = seqs
+        .iter()
+        .enumerate()
+        .filter_map(|(i, seq)| {
+            find_best_site(seq, acid, genome, expected, padding, t, params).map(|s| (i, s))
+        })
+        .min_by(|a, b| a.1.score.total_cmp(&b.1.score));
+
+    match best {
+        Some((seq_index, site)) => {
+            let seq = &seqs[seq_index];
+            let match_bool: Vec<bool> = seq
+                .iter()
+                .zip(site.target.iter())
+                .map(|(s, t)| s == t)
+                .collect();
+            let mismatches = match_bool
+                .iter()
+                .rev()
+                .enumerate()
+                .filter(|(_i, is_match)| !**is_match)
+                .map(|(i, _is_match)| i)
+                .collect();
+
+            GenomeBinding {
+                seq_index: Some(seq_index),
+                extension_score: calc_extention(seq, &match_bool),
+                mismatches,
+                binds: site.score <= t,
+                site: Some(site),
+            }
+        }
+        None => GenomeBinding {
+            seq_index: None,
+            site: None,
+            mismatches: Vec::new(),
+            extension_score: None,
+            binds: false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{encode_base, DEFAULT_PARAMS};
+    use super::*;
+
+    // nCoV-2019_76_RIGHT_0 in a G flank
+    fn genome(primer: &str) -> Vec<usize> {
+        let mut genome = encode_base("GGGGGGGGGGGGGGGGGGGG");
+        genome.extend(encode_base(primer));
+        genome.extend(encode_base("GGGGGGGGGGGGGGGGGGGG"));
+        genome
+    }
+
+    #[test]
+    fn test_perfect_binding() {
+        let seqs = vec![encode_base("ACACCTGTGCCTGTTAAACCAT")];
+        let expected = Site {
+            start: 20,
+            end: 42,
+            strand: Strand::Forward,
+        };
+        let binding = calc_genome_binding(
+            &seqs,
+            NucleicAcid::Dna,
+            &genome("ACACCTGTGCCTGTTAAACCAT"),
+            Some(expected),
+            10,
+            -30.,
+            &DEFAULT_PARAMS,
+        );
+        assert_eq!(binding.seq_index, Some(0));
+        assert_eq!(binding.site.unwrap().site, expected);
+        assert!(binding.mismatches.is_empty());
+        assert!(binding.binds);
+    }
+    #[test]
+    fn test_no_padding() {
+        // The expected site is found on either strand, with only it searched
+        let primer = encode_base("ACACCTGTGCCTGTTAAACCAT");
+        let seqs = vec![primer.clone()];
+        let mut genome = genome("ACACCTGTGCCTGTTAAACCAT");
+        genome.extend(primer.iter().rev().map(|b| 3 - b));
+        genome.extend(encode_base("GGGGGGGGGGGGGGGGGGGG"));
+        for (start, strand) in [(20, Strand::Forward), (62, Strand::Reverse)] {
+            let expected = Site {
+                start,
+                end: start + 22,
+                strand,
+            };
+            let binding = calc_genome_binding(
+                &seqs,
+                NucleicAcid::Dna,
+                &genome,
+                Some(expected),
+                0,
+                -30.,
+                &DEFAULT_PARAMS,
+            );
+            assert_eq!(binding.site.unwrap().site, expected);
+            assert!(binding.binds);
+        }
+    }
+    #[test]
+    fn test_3p_mismatches() {
+        // The final two 3' bases differ, so the primer can't extend at the expected site.
+        // It is still reported there, with its mismatches
+        let seqs = vec![encode_base("ACACCTGTGCCTGTTAAACCAT")];
+        let expected = Site {
+            start: 20,
+            end: 42,
+            strand: Strand::Forward,
+        };
+        let binding = calc_genome_binding(
+            &seqs,
+            NucleicAcid::Dna,
+            &genome("ACACCTGTGCCTGTTAAACCTA"),
+            Some(expected),
+            1,
+            -30.,
+            &DEFAULT_PARAMS,
+        );
+        assert!(!binding.binds);
+        assert_eq!(binding.site.map(|s| s.site), Some(expected));
+        assert_eq!(binding.mismatches, vec![0, 1]);
+        assert_eq!(binding.extension_score, None);
+    }
+    #[test]
+    fn test_best_seq_in_kmer() {
+        // The second seq matches the variant
+        let seqs = vec![
+            encode_base("ACACCTGTGCCTGTTAAACCAT"),
+            encode_base("ACACCTGAGCCTGTTAAACCAT"),
+        ];
+        let binding = calc_genome_binding(
+            &seqs,
+            NucleicAcid::Dna,
+            &genome("ACACCTGAGCCTGTTAAACCAT"),
+            None,
+            10,
+            -30.,
+            &DEFAULT_PARAMS,
+        );
+        assert_eq!(binding.seq_index, Some(1));
+        assert!(binding.mismatches.is_empty());
+        assert!(binding.extension_score.is_some());
+    }
+    #[test]
+    fn test_internal_mismatch() {
+        let seqs = vec![encode_base("ACACCTGTGCCTGTTAAACCAT")];
+        let binding = calc_genome_binding(
+            &seqs,
+            NucleicAcid::Dna,
+            &genome("ACACCTGAGCCTGTTAAACCAT"),
+            None,
+            10,
+            -30.,
+            &DEFAULT_PARAMS,
+        );
+        // T -> A, 14 bases from the 3' end
+        assert_eq!(binding.mismatches, vec![14]);
+        assert!(binding.binds);
+    }
+}